@@ -1,19 +1,39 @@
 use core::alloc::{AllocError, Allocator, Layout};
 use core::ptr::{self, NonNull};
-use std::alloc::{alloc, dealloc};
+use std::alloc::{alloc, dealloc, handle_alloc_error};
 use std::collections::BinaryHeap;
 use std::sync::Mutex;
 
 impl StacklikeAlloc {
     pub(crate) fn new(layout: Layout) -> Self {
         let memlyt = layout.pad_to_align();
+        // Allocations would be handed out relative to a null pointer.
+        let memory = unsafe { alloc(memlyt) };
+        if memory.is_null() {
+            handle_alloc_error(memlyt);
+        }
 
         Self {
             contrl: Mutex::new(StacklikeCtrl::new()),
             memlyt,
-            memory: unsafe { alloc(memlyt) },
+            memory,
         }
     }
+
+    /// Bytes of the whole arena.
+    pub(crate) fn size(&self) -> usize {
+        self.memlyt.size()
+    }
+
+    /// Bytes between the start of the arena and its current top.
+    pub(crate) fn used(&self) -> usize {
+        self.contrl.lock().unwrap().offset
+    }
+
+    /// Highest top of the arena so far.
+    pub(crate) fn peak(&self) -> usize {
+        self.contrl.lock().unwrap().peak
+    }
 }
 
 unsafe impl Sync for StacklikeAlloc {}
@@ -32,7 +52,7 @@ unsafe impl Allocator for StacklikeAlloc {
 
         let mut contrl = self.contrl.lock().unwrap();
         let mask = layout.align() - 1;
-        let start = contrl.offset + mask & !mask;
+        let start = (contrl.offset + mask) & !mask;
         let end = start + layout.size();
 
         if end > self.memlyt.size() {
@@ -43,6 +63,7 @@ unsafe impl Allocator for StacklikeAlloc {
         let offset = contrl.offset;
         contrl.alptrs.push(offset);
         contrl.offset = end;
+        contrl.peak = contrl.peak.max(end);
         Ok(unsafe { NonNull::new_unchecked(ptr) })
     }
 
@@ -74,6 +95,7 @@ impl StacklikeCtrl {
     fn new() -> Self {
         Self {
             offset: 0,
+            peak: 0,
             alptrs: Vec::new(),
             frptrs: BinaryHeap::new(),
         }
@@ -82,6 +104,7 @@ impl StacklikeCtrl {
 
 pub(crate) struct StacklikeCtrl {
     offset: usize,
+    peak: usize,
     alptrs: Vec<usize>,
     frptrs: BinaryHeap<usize>,
 }
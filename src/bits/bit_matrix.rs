@@ -1,4 +1,4 @@
-use crate::bits::bit_tools::{Bits, indices, integers_needed};
+use crate::bits::bit_tools::{Bits, POWR, indices, integers_needed};
 
 use core::alloc::{Allocator, Layout};
 use core::ptr::NonNull;
@@ -63,14 +63,6 @@ impl<A: Allocator> BitMatrix<A> {
         self.col_count
     }
 
-    pub(crate) const fn row_capacity(&self) -> usize {
-        self.row_capac
-    }
-
-    pub(crate) const fn col_capacity(&self) -> usize {
-        self.col_capac
-    }
-
     pub(crate) fn read(&self, row: usize, col: usize) -> bool {
         assert!(row < self.row_count, "Row is out of bound.");
         assert!(col < self.col_count, "Col is out of bound.");
@@ -79,8 +71,7 @@ impl<A: Allocator> BitMatrix<A> {
         i += row * self.integers_needed_each_row();
 
         let num = *unsafe { self.rc_memory.unwrap().add(i).as_ref() };
-        let res = num.read(j);
-        res
+        num.read(j)
     }
 
     fn read_alt(&self, row: usize, col: usize) -> bool {
@@ -106,19 +97,21 @@ impl<A: Allocator> BitMatrix<A> {
         true
     }
 
-    pub(crate) fn write(&mut self, row: usize, col: usize, value: bool) {
-        if value {
-            self.set(row, col);
-        } else {
-            self.unset(row, col);
-        }
-        debug_assert!(self.is_consistent(), "Data is inconsistent after write.");
-    }
-
     impl_mut_row_col_access!(set);
     impl_mut_row_col_access!(unset);
     impl_mut_row_col_access!(flip);
 
+    /// Add row `src` onto row `dst` over GF(2).
+    pub(crate) fn xor_row(&mut self, dst: usize, src: usize) {
+        for i in 0..self.integers_used_each_row() {
+            let mut x = self.row_data(src)[i];
+            while x != 0 {
+                self.flip(dst, (i << POWR) + x.trailing_zeros() as usize);
+                x &= x - 1;
+            }
+        }
+    }
+
     pub(crate) fn row_data(&self, row: usize) -> &[usize] {
         assert!(row < self.row_count);
         if let Some(ptr) = self.rc_memory {
@@ -340,9 +333,8 @@ impl<A: Allocator + Clone> Clone for BitMatrix<A> {
         let allocator = self.allocator.clone();
         let mut res = Self::with_capacity_in(self.row_count, self.col_count, allocator);
 
-        if let Some(dst) = res.rc_memory {
-            let src = self.rc_memory.unwrap();
-
+        // Columns without any row capacity have no memory yet.
+        if let (Some(dst), Some(src)) = (res.rc_memory, self.rc_memory) {
             unsafe {
                 let src_needed = self.integers_needed_each_row();
                 let dst_needed = res.integers_needed_each_row();
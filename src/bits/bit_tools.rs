@@ -9,7 +9,7 @@ pub(crate) const fn indices(index: usize) -> (usize, usize) {
 }
 
 pub(crate) const fn integers_needed(bits: usize) -> usize {
-    bits + MASK >> POWR
+    (bits + MASK) >> POWR
 }
 
 macro_rules! impl_bit_manipulation {
@@ -163,43 +163,6 @@ macro_rules! uint_slice_iter_ones {
 
 uint_slice_iter_ones!(usize, IterOnesSliceUsize);
 
-macro_rules! uint_iterator_iter_ones {
-    (
-        $uint:ty,
-        $IterOnesIterator:ident
-    ) => {
-        impl<I: Iterator<Item = $uint>> $IterOnesIterator<I> {
-            pub(crate) const fn new(iterator: I) -> $IterOnesIterator<I> {
-                $IterOnesIterator(0, 0, iterator)
-            }
-        }
-
-        impl<I> FusedIterator for $IterOnesIterator<I> where
-            I: Iterator<Item = $uint> + FusedIterator
-        {
-        }
-
-        impl<I: Iterator<Item = $uint>> Iterator for $IterOnesIterator<I> {
-            type Item = usize;
-
-            fn next(&mut self) -> Option<Self::Item> {
-                while self.0 == 0 {
-                    self.0 = self.2.next()?;
-                    self.1 += BITS;
-                }
-
-                let res = self.0.trailing_zeros() as usize + self.1 - BITS;
-                self.0 &= self.0 - 1;
-                Some(res)
-            }
-        }
-
-        pub(crate) struct $IterOnesIterator<I: Iterator<Item = $uint>>($uint, $uint, I);
-    };
-}
-
-uint_iterator_iter_ones!(usize, IterOnesIteratorUsize);
-
-pub(crate) const fn iter_ones_slice_usize(slice: &[usize]) -> IterOnesSliceUsize {
+pub(crate) const fn iter_ones_slice_usize(slice: &[usize]) -> IterOnesSliceUsize<'_> {
     IterOnesSliceUsize::new(slice)
 }
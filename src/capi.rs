@@ -0,0 +1,192 @@
+//! The IPASIR interface, see <https://github.com/biotomas/ipasir>.
+//!
+//! One `Solver` keeps the clauses and grows with them, every call to
+//! `ipasir_solve` solves it under the assumptions collected since the last.
+//!
+//! The shared library to link against is built along with the Rust library,
+//! e.g. by `cargo build --release --features capi`.
+
+use crate::solver::{Solution, Solver};
+
+use core::ffi::{c_char, c_int, c_void};
+use std::mem::take;
+
+impl Ipasir {
+    fn new() -> Self {
+        Self {
+            solver: Solver::new(0, 0),
+            vrs: 0,
+            clauses: 0,
+            clause: Vec::new(),
+            assumptions: Vec::new(),
+            failed: Vec::new(),
+            model: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, lit: i32) {
+        if lit == 0 {
+            self.clauses += 1;
+            self.solver
+                .reserve(self.vrs, self.clauses)
+                .and_then(|_| self.solver.add_clause(take(&mut self.clause)))
+                .expect("Clause is valid by construction.");
+        } else {
+            self.vrs = self.vrs.max(lit.unsigned_abs() as usize);
+            self.clause.push(lit as isize);
+        }
+    }
+
+    fn assume(&mut self, lit: i32) {
+        self.vrs = self.vrs.max(lit.unsigned_abs() as usize);
+        self.assumptions.push(lit as isize);
+    }
+
+    fn solve(&mut self) -> c_int {
+        self.model.clear();
+        self.failed.clear();
+        let assumptions = take(&mut self.assumptions);
+
+        let result = self
+            .solver
+            .reserve(self.vrs, self.clauses)
+            .and_then(|_| self.solver.solve_assuming(&assumptions));
+        match result {
+            Ok(Solution::Satisfiable(x)) => {
+                self.model = x;
+                10
+            }
+            Ok(Solution::Unsatisfiable) => {
+                // There is no conflict analysis, so all assumptions are blamed.
+                self.failed = assumptions;
+                20
+            }
+            _ => 0,
+        }
+    }
+
+    fn val(&self, lit: i32) -> i32 {
+        match self.model.get(lit.unsigned_abs() as usize - 1) {
+            Some(&x) if x.signum() == lit.signum() as isize => lit,
+            Some(_) => -lit,
+            None => 0,
+        }
+    }
+
+    fn failed(&self, lit: i32) -> c_int {
+        self.failed.contains(&(lit as isize)) as c_int
+    }
+}
+
+impl Terminate {
+    fn poll(&self) -> bool {
+        (self.func)(self.data) != 0
+    }
+}
+
+struct Ipasir {
+    solver: Solver,
+    vrs: usize,
+    clauses: usize,
+    clause: Vec<isize>,
+    assumptions: Vec<isize>,
+    failed: Vec<isize>,
+    model: Vec<isize>,
+}
+
+#[derive(Clone, Copy)]
+struct Terminate {
+    data: *mut c_void,
+    func: extern "C" fn(*mut c_void) -> c_int,
+}
+
+// The callback is only ever polled on the thread calling `ipasir_solve`.
+unsafe impl Send for Terminate {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn ipasir_signature() -> *const c_char {
+    concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "\0")
+        .as_ptr()
+        .cast()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn ipasir_init() -> *mut c_void {
+    Box::into_raw(Box::new(Ipasir::new())).cast()
+}
+
+/// # Safety
+/// `solver` must come from `ipasir_init` and is invalid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_release(solver: *mut c_void) {
+    drop(unsafe { Box::from_raw(solver.cast::<Ipasir>()) });
+}
+
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_add(solver: *mut c_void, lit_or_zero: i32) {
+    unsafe { &mut *solver.cast::<Ipasir>() }.add(lit_or_zero);
+}
+
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_assume(solver: *mut c_void, lit: i32) {
+    unsafe { &mut *solver.cast::<Ipasir>() }.assume(lit);
+}
+
+/// Returns 10 if satisfiable, 20 if unsatisfiable and 0 if interrupted.
+///
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_solve(solver: *mut c_void) -> c_int {
+    unsafe { &mut *solver.cast::<Ipasir>() }.solve()
+}
+
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_val(solver: *mut c_void, lit: i32) -> i32 {
+    unsafe { &*solver.cast::<Ipasir>() }.val(lit)
+}
+
+/// Returns 1 for every assumption of the last unsatisfiable `ipasir_solve`,
+/// 0 otherwise. Without conflict analysis no smaller core is known, so this
+/// over-approximates the failed assumptions: all of them together are
+/// inconsistent with the clauses, but some may not be needed for that.
+///
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_failed(solver: *mut c_void, lit: i32) -> c_int {
+    unsafe { &*solver.cast::<Ipasir>() }.failed(lit)
+}
+
+/// # Safety
+/// `solver` must come from `ipasir_init`, `data` must stay valid for `terminate`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_set_terminate(
+    solver: *mut c_void,
+    data: *mut c_void,
+    terminate: Option<extern "C" fn(*mut c_void) -> c_int>,
+) {
+    let terminate = terminate.map(|func| Terminate { data, func });
+    unsafe { &mut *solver.cast::<Ipasir>() }
+        .solver
+        .set_terminate(terminate.map(|x| Box::new(move || x.poll()) as Box<_>));
+}
+
+/// Learnt clauses are never reported, since the search does not learn any.
+///
+/// # Safety
+/// `solver` must come from `ipasir_init`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ipasir_set_learn(
+    _solver: *mut c_void,
+    _data: *mut c_void,
+    _max_length: c_int,
+    _learn: Option<extern "C" fn(*mut c_void, *mut i32)>,
+) {
+}
@@ -0,0 +1,67 @@
+use core::fmt;
+
+impl Circuit {
+    pub fn gates(&self) -> &[Gate] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, gate: Gate) {
+        self.0.push(gate);
+    }
+}
+
+/// One line `output = kind inputs` per gate, AND gates with a negative output
+/// are written as OR gates.
+impl fmt::Display for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for gate in &self.0 {
+            let (sign, kind) = match gate.kind {
+                GateKind::And if gate.output < 0 => (-1, "or"),
+                GateKind::And => (1, "and"),
+                GateKind::Xor => (1, "xor"),
+                GateKind::Ite => (1, "ite"),
+                GateKind::Majority => (1, "maj"),
+            };
+            write!(f, "{} = {kind}", sign * gate.output)?;
+            for l in &gate.inputs {
+                write!(f, " {}", sign * l)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The function of its inputs a gate computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateKind {
+    /// All inputs are true.
+    And,
+    /// An odd number of inputs is true.
+    Xor,
+    /// The second input if the first is true, the third otherwise.
+    Ite,
+    /// At least two of three inputs are true.
+    Majority,
+}
+
+/// A literal defined by its clauses as a function of other literals, all of
+/// them in DIMACS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gate {
+    pub output: isize,
+    pub kind: GateKind,
+    pub inputs: Vec<isize>,
+}
+
+/// Gates recognized in the clauses, at most one per variable.
+#[derive(Debug, Clone, Default)]
+pub struct Circuit(Vec<Gate>);
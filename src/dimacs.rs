@@ -0,0 +1,953 @@
+use core::fmt;
+use core::num::IntErrorKind;
+use std::io::{self, BufRead, Write};
+
+impl<R: BufRead> Lexer<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            tokens: Vec::new(),
+            line: 0,
+            column: 0,
+            ended: false,
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    /// The next token and its column, skipping comment lines. A line starting
+    /// with `%` ends the formula, as in the SATLIB benchmarks.
+    fn next_token(&mut self) -> Result<Option<(usize, String)>, ParseError> {
+        while self.tokens.is_empty() {
+            if self.ended {
+                return Ok(None);
+            }
+            self.buffer.clear();
+            let read = self.reader.read_line(&mut self.buffer);
+            match read.map_err(|x| self.error(ErrorKind::Io(x)))? {
+                0 => self.ended = true,
+                _ => self.line += 1,
+            }
+            let line = self.buffer.trim_start();
+            if line.starts_with('%') {
+                self.ended = true;
+            }
+            if self.ended {
+                return Ok(None);
+            }
+            if line.starts_with('c') {
+                continue;
+            }
+            let start = self.buffer.as_ptr() as usize;
+            self.tokens.extend(
+                self.buffer
+                    .split_ascii_whitespace()
+                    .map(|x| (x.as_ptr() as usize - start + 1, x.to_string()))
+                    .rev(),
+            );
+        }
+        let token = self.tokens.pop();
+        if let Some((column, _)) = token {
+            self.column = column;
+        }
+        Ok(token)
+    }
+
+    /// Hand `token` out again by the next call of `next_token`.
+    fn push_back(&mut self, token: (usize, String)) {
+        self.tokens.push(token);
+    }
+
+    /// The tokens left on the current line.
+    fn rest_of_line(&mut self) -> Vec<(usize, String)> {
+        self.tokens.drain(..).rev().collect()
+    }
+
+    fn number(&mut self, column: usize, token: &str) -> Result<usize, ParseError> {
+        self.column = column;
+        token
+            .parse()
+            .map_err(|_| self.error(ErrorKind::NotANumber(token.to_string())))
+    }
+
+    /// A positive weight, `None` if `token` is no integer at all.
+    fn weight(&mut self, column: usize, token: &str) -> Result<Option<u64>, ParseError> {
+        self.column = column;
+        let negative = token
+            .strip_prefix('-')
+            .is_some_and(|x| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()));
+        match token.parse::<u64>() {
+            Ok(0) => Err(self.error(ErrorKind::NonPositiveWeight(token.to_string()))),
+            Ok(x) => Ok(Some(x)),
+            Err(x) if *x.kind() == IntErrorKind::PosOverflow => {
+                Err(self.error(ErrorKind::WeightOverflow(token.to_string())))
+            }
+            Err(_) if negative => Err(self.error(ErrorKind::NonPositiveWeight(token.to_string()))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl<R: BufRead> Parser<R> {
+    /// A strict parser rejects what a lenient one lets pass: header counts
+    /// that do not match, variables above the declared number, an unterminated
+    /// last clause and garbage after the declared clauses, which ends the
+    /// formula otherwise.
+    pub fn new(reader: R, strict: bool) -> Self {
+        Self {
+            lexer: Lexer::new(reader),
+            strict,
+            header: None,
+            prefix: Vec::new(),
+            clauses: 0,
+            done: false,
+        }
+    }
+
+    /// Read the header and the quantifier prefix of QDIMACS following it,
+    /// comment lines before it are skipped.
+    pub fn header(&mut self) -> Result<Header, ParseError> {
+        if let Some(x) = self.header {
+            return Ok(x);
+        }
+        match self.lexer.next_token()? {
+            Some((_, x)) if x == "p" => (),
+            _ => return Err(self.lexer.error(ErrorKind::MissingHeader("p cnf"))),
+        }
+
+        let header = match &self.lexer.rest_of_line()[..] {
+            [(_, cnf), (i, vars), (j, clauses), rest @ ..] if cnf == "cnf" => {
+                if self.strict && !rest.is_empty() {
+                    self.lexer.column = rest[0].0;
+                    return Err(self.lexer.error(ErrorKind::InvalidHeader(CNF_HEADER)));
+                }
+                Header {
+                    variables: self.lexer.number(*i, vars)?,
+                    clauses: self.lexer.number(*j, clauses)?,
+                }
+            }
+            _ => return Err(self.lexer.error(ErrorKind::InvalidHeader(CNF_HEADER))),
+        };
+        self.header = Some(header);
+        self.read_prefix(header)?;
+        Ok(header)
+    }
+
+    /// The quantifier blocks of QDIMACS from the outside in, empty for plain
+    /// DIMACS. The header is read first if needed.
+    pub fn prefix(&mut self) -> Result<&[Block], ParseError> {
+        self.header()?;
+        Ok(&self.prefix)
+    }
+
+    /// Clauses counted so far.
+    pub fn clauses(&self) -> usize {
+        self.clauses
+    }
+
+    /// Read the `e` and `a` lines in front of the clauses.
+    fn read_prefix(&mut self, header: Header) -> Result<(), ParseError> {
+        let mut bound = Vec::new();
+        loop {
+            let universal = match self.lexer.next_token()? {
+                Some((_, x)) if x == "e" => false,
+                Some((_, x)) if x == "a" => true,
+                Some(x) => {
+                    self.lexer.push_back(x);
+                    return Ok(());
+                }
+                None => return Ok(()),
+            };
+            let mut variables = Vec::new();
+            loop {
+                let token = match self.lexer.next_token()? {
+                    Some((_, x)) => x,
+                    None => return Err(self.lexer.error(ErrorKind::UnterminatedClause)),
+                };
+                let var = match token.parse::<usize>() {
+                    Ok(0) => break,
+                    Ok(x) => x,
+                    Err(_) => return Err(self.lexer.error(ErrorKind::NotANumber(token))),
+                };
+                if self.strict && var > header.variables {
+                    let kind = ErrorKind::VariableTooLarge(var as isize);
+                    return Err(self.lexer.error(kind));
+                }
+                if self.strict && bound.contains(&var) {
+                    return Err(self.lexer.error(ErrorKind::BoundTwice(var)));
+                }
+                bound.push(var);
+                variables.push(var);
+            }
+            // Consecutive blocks of the same quantifier are one.
+            match self.prefix.last_mut() {
+                Some(x) if x.universal == universal => x.variables.extend(variables),
+                _ => self.prefix.push(Block {
+                    universal,
+                    variables,
+                }),
+            }
+        }
+    }
+
+    /// Count a finished constraint.
+    fn finish(&mut self, literals: Vec<isize>, xor: bool) -> Result<Constraint, ParseError> {
+        self.clauses += 1;
+        if self.strict && self.clauses > self.header.map_or(0, |x| x.clauses) {
+            return Err(self.lexer.error(ErrorKind::TooManyClauses));
+        }
+        Ok(match xor {
+            true => Constraint::Xor(literals),
+            false => Constraint::Clause(literals),
+        })
+    }
+
+    /// The end of the input, where a pending constraint is finished unless
+    /// strict. A strict parser also wants all declared clauses.
+    fn end(&mut self, literals: Vec<isize>, xor: bool) -> Option<Result<Constraint, ParseError>> {
+        self.done = true;
+        if !literals.is_empty() || xor {
+            return Some(match self.strict {
+                true => Err(self.lexer.error(ErrorKind::UnterminatedClause)),
+                false => self.finish(literals, xor),
+            });
+        }
+        let declared = self.header.map_or(0, |x| x.clauses);
+        (self.strict && self.clauses < declared)
+            .then(|| Err(self.lexer.error(ErrorKind::TooFewClauses)))
+    }
+
+    fn constraint(&mut self) -> Option<Result<Constraint, ParseError>> {
+        let header = match self.header() {
+            Ok(x) => x,
+            Err(x) => return Some(Err(x)),
+        };
+        let mut literals = Vec::new();
+        let mut xor = false;
+        loop {
+            let token = match self.lexer.next_token() {
+                Ok(Some((_, x))) => x,
+                Ok(None) => return self.end(literals, xor),
+                Err(x) => return Some(Err(x)),
+            };
+            // XOR constraints of the CryptoMiniSat extension.
+            let mut token = token.as_str();
+            if literals.is_empty() && !xor && token.starts_with('x') {
+                xor = true;
+                token = &token[1..];
+                if token.is_empty() {
+                    continue;
+                }
+            }
+
+            match token.parse::<isize>() {
+                Ok(0) => return Some(self.finish(literals, xor)),
+                Ok(x) if self.strict && x.unsigned_abs() > header.variables => {
+                    return Some(Err(self.lexer.error(ErrorKind::VariableTooLarge(x))));
+                }
+                Ok(x) => literals.push(x),
+                // Garbage only ends the formula after the declared clauses.
+                Err(_) if !self.strict && self.clauses >= header.clauses => {
+                    return self.end(literals, xor);
+                }
+                Err(_) => {
+                    let kind = ErrorKind::NotANumber(token.to_string());
+                    return Some(Err(self.lexer.error(kind)));
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> WeightedParser<R> {
+    /// Strict like `Parser::new`, the declared counts only exist in the
+    /// classic format.
+    pub fn new(reader: R, strict: bool) -> Self {
+        Self {
+            lexer: Lexer::new(reader),
+            strict,
+            header: None,
+            clauses: 0,
+            total: 0,
+            done: false,
+        }
+    }
+
+    /// Read the header of the classic format, `None` for the MaxSAT 2022
+    /// format without one. Comment lines before it are skipped.
+    pub fn header(&mut self) -> Result<Option<WeightedHeader>, ParseError> {
+        if let Some(x) = self.header {
+            return Ok(x);
+        }
+        let token = match self.lexer.next_token()? {
+            Some((_, x)) if x == "p" => None,
+            x => x,
+        };
+        if let Some(x) = token {
+            self.lexer.push_back(x);
+            self.header = Some(None);
+            return Ok(None);
+        }
+
+        let header = match &self.lexer.rest_of_line()[..] {
+            [(_, wcnf), (i, vars), (j, clauses), rest @ ..] if wcnf == "wcnf" => {
+                if self.strict && rest.len() > 1 {
+                    self.lexer.column = rest[1].0;
+                    return Err(self.lexer.error(ErrorKind::InvalidHeader(WCNF_HEADER)));
+                }
+                let top = match rest.first() {
+                    Some((k, top)) => match self.lexer.weight(*k, top)? {
+                        Some(x) => Some(x),
+                        None => {
+                            let kind = ErrorKind::NotANumber(top.to_string());
+                            return Err(self.lexer.error(kind));
+                        }
+                    },
+                    None => None,
+                };
+                WeightedHeader {
+                    variables: self.lexer.number(*i, vars)?,
+                    clauses: self.lexer.number(*j, clauses)?,
+                    top,
+                }
+            }
+            _ => return Err(self.lexer.error(ErrorKind::InvalidHeader(WCNF_HEADER))),
+        };
+        self.header = Some(Some(header));
+        Ok(Some(header))
+    }
+
+    /// Clauses counted so far.
+    pub fn clauses(&self) -> usize {
+        self.clauses
+    }
+
+    /// The sum of the soft weights so far.
+    pub fn total_weight(&self) -> u64 {
+        self.total
+    }
+
+    /// Count a finished clause, the soft weights must sum up to a `u64`.
+    fn finish(
+        &mut self,
+        weight: Option<u64>,
+        literals: Vec<isize>,
+    ) -> Result<WeightedClause, ParseError> {
+        self.clauses += 1;
+        let declared = self.header.flatten().map(|x| x.clauses);
+        if self.strict && declared.is_some_and(|x| self.clauses > x) {
+            return Err(self.lexer.error(ErrorKind::TooManyClauses));
+        }
+        let Some(weight) = weight else {
+            return Ok(WeightedClause::Hard(literals));
+        };
+        self.total = match self.total.checked_add(weight) {
+            Some(x) => x,
+            None => {
+                let kind = ErrorKind::WeightOverflow(weight.to_string());
+                return Err(self.lexer.error(kind));
+            }
+        };
+        Ok(WeightedClause::Soft(weight, literals))
+    }
+
+    /// The end of the input, see `Parser::end`.
+    fn end(
+        &mut self,
+        clause: Option<(Option<u64>, Vec<isize>)>,
+    ) -> Option<Result<WeightedClause, ParseError>> {
+        self.done = true;
+        if let Some((weight, literals)) = clause {
+            return Some(match self.strict {
+                true => Err(self.lexer.error(ErrorKind::UnterminatedClause)),
+                false => self.finish(weight, literals),
+            });
+        }
+        let declared = self.header.flatten().map_or(0, |x| x.clauses);
+        (self.strict && self.clauses < declared)
+            .then(|| Err(self.lexer.error(ErrorKind::TooFewClauses)))
+    }
+
+    /// The weight of the next clause, `None` if hard. Garbage or the end of
+    /// the input give `Err(None)`, which ends the formula.
+    fn clause_weight(&mut self) -> Result<Option<u64>, Option<ParseError>> {
+        let (column, token) = match self.lexer.next_token() {
+            Ok(Some(x)) => x,
+            Ok(None) => return Err(None),
+            Err(x) => return Err(Some(x)),
+        };
+        let header = self.header.flatten();
+        if header.is_none() && token == "h" {
+            return Ok(None);
+        }
+        let top = header.and_then(|x| x.top);
+        match self.lexer.weight(column, &token) {
+            Ok(Some(x)) if top.is_some_and(|top| x >= top) => Ok(None),
+            Ok(Some(x)) => Ok(Some(x)),
+            Ok(None) if self.strict => Err(Some(self.lexer.error(ErrorKind::NotANumber(token)))),
+            Ok(None) => Err(None),
+            Err(x) => Err(Some(x)),
+        }
+    }
+
+    fn clause(&mut self) -> Option<Result<WeightedClause, ParseError>> {
+        let header = match self.header() {
+            Ok(x) => x,
+            Err(x) => return Some(Err(x)),
+        };
+        let weight = match self.clause_weight() {
+            Ok(x) => x,
+            Err(None) => return self.end(None),
+            Err(Some(x)) => return Some(Err(x)),
+        };
+        let mut literals = Vec::new();
+        loop {
+            let token = match self.lexer.next_token() {
+                Ok(Some((_, x))) => x,
+                Ok(None) => return self.end(Some((weight, literals))),
+                Err(x) => return Some(Err(x)),
+            };
+            match token.parse::<isize>() {
+                Ok(0) => return Some(self.finish(weight, literals)),
+                Ok(x) if self.strict && header.is_some_and(|h| x.unsigned_abs() > h.variables) => {
+                    return Some(Err(self.lexer.error(ErrorKind::VariableTooLarge(x))));
+                }
+                Ok(x) => literals.push(x),
+                Err(_) if self.strict => {
+                    return Some(Err(self.lexer.error(ErrorKind::NotANumber(token))));
+                }
+                Err(_) => return self.end(Some((weight, literals))),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> IncrementalParser<R> {
+    /// Strict like `Parser::new`, but there are no declared counts.
+    pub fn new(reader: R, strict: bool) -> Self {
+        Self {
+            lexer: Lexer::new(reader),
+            strict,
+            header: false,
+            done: false,
+        }
+    }
+
+    /// Read the `p inccnf` header, comment lines before it are skipped.
+    pub fn header(&mut self) -> Result<(), ParseError> {
+        if self.header {
+            return Ok(());
+        }
+        match self.lexer.next_token()? {
+            Some((_, x)) if x == "p" => (),
+            _ => return Err(self.lexer.error(ErrorKind::MissingHeader("p inccnf"))),
+        }
+        match &self.lexer.rest_of_line()[..] {
+            [(_, inccnf), rest @ ..] if inccnf == "inccnf" => {
+                if self.strict && !rest.is_empty() {
+                    self.lexer.column = rest[0].0;
+                    return Err(self.lexer.error(ErrorKind::InvalidHeader(INCCNF_HEADER)));
+                }
+            }
+            _ => return Err(self.lexer.error(ErrorKind::InvalidHeader(INCCNF_HEADER))),
+        }
+        self.header = true;
+        Ok(())
+    }
+
+    /// The end of the input, where a pending query is finished unless strict.
+    fn end(&mut self, query: Option<Query>) -> Option<Result<Query, ParseError>> {
+        self.done = true;
+        match self.strict {
+            true => query.map(|_| Err(self.lexer.error(ErrorKind::UnterminatedClause))),
+            false => query.map(Ok),
+        }
+    }
+
+    fn query(&mut self) -> Option<Result<Query, ParseError>> {
+        if let Err(x) = self.header() {
+            return Some(Err(x));
+        }
+        let mut query = None;
+        loop {
+            let token = match self.lexer.next_token() {
+                Ok(Some((_, x))) => x,
+                Ok(None) => return self.end(query),
+                Err(x) => return Some(Err(x)),
+            };
+            if query.is_none() && token == "a" {
+                query = Some(Query::Assume(Vec::new()));
+                continue;
+            }
+            let literals = match query.get_or_insert(Query::Clause(Vec::new())) {
+                Query::Clause(x) | Query::Assume(x) => x,
+            };
+            match token.parse::<isize>() {
+                Ok(0) => return query.map(Ok),
+                Ok(x) => literals.push(x),
+                Err(_) if self.strict => {
+                    return Some(Err(self.lexer.error(ErrorKind::NotANumber(token))));
+                }
+                // Garbage starting a line is no pending query.
+                Err(_) if literals.is_empty() => {
+                    let query = query.filter(|x| matches!(x, Query::Assume(_)));
+                    return self.end(query);
+                }
+                Err(_) => return self.end(query),
+            }
+        }
+    }
+}
+
+impl Constraint {
+    pub fn literals(&self) -> &[isize] {
+        match self {
+            Self::Clause(x) | Self::Xor(x) => x,
+        }
+    }
+}
+
+impl WeightedClause {
+    pub fn literals(&self) -> &[isize] {
+        match self {
+            Self::Hard(x) | Self::Soft(_, x) => x,
+        }
+    }
+
+    /// `None` for hard clauses.
+    pub fn weight(&self) -> Option<u64> {
+        match self {
+            Self::Hard(_) => None,
+            Self::Soft(x, _) => Some(*x),
+        }
+    }
+}
+
+/// Write `constraints` over `variables` in DIMACS CNF. The `comments` go
+/// before the header and must be `c` lines, like `Statistics` displays.
+pub fn write<W: Write>(
+    out: &mut W,
+    variables: usize,
+    constraints: &[Constraint],
+    comments: &str,
+) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    out.write_all(comments.as_bytes())?;
+    if !comments.is_empty() && !comments.ends_with('\n') {
+        writeln!(out)?;
+    }
+    writeln!(out, "p cnf {} {}", variables, constraints.len())?;
+    for x in constraints {
+        writeln!(out, "{x}")?;
+    }
+    out.flush()
+}
+
+/// Constraints one at a time, the header is read first if needed. After an
+/// error nothing follows.
+impl<R: BufRead> Iterator for Parser<R> {
+    type Item = Result<Constraint, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.constraint();
+        self.done |= !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+/// Queries one at a time like `Parser`.
+impl<R: BufRead> Iterator for IncrementalParser<R> {
+    type Item = Result<Query, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.query();
+        self.done |= !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+/// Clauses one at a time like `Parser`.
+impl<R: BufRead> Iterator for WeightedParser<R> {
+    type Item = Result<WeightedClause, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.clause();
+        self.done |= !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(x) => write!(f, "{x}"),
+            Self::MissingHeader(x) => write!(f, "input must start with a '{x}' header"),
+            Self::InvalidHeader(x) => write!(f, "header must be '{x}'"),
+            Self::NotANumber(x) => write!(f, "'{x}' is no integer"),
+            Self::VariableTooLarge(x) => write!(f, "literal {x} exceeds the declared variables"),
+            Self::TooManyClauses => write!(f, "more clauses than declared"),
+            Self::TooFewClauses => write!(f, "fewer clauses than declared"),
+            Self::UnterminatedClause => write!(f, "last clause is not terminated by 0"),
+            Self::NonPositiveWeight(x) => write!(f, "weight {x} is not positive"),
+            Self::WeightOverflow(x) => write!(f, "weight {x} overflows 64 bits"),
+            Self::BoundTwice(x) => write!(f, "variable {x} is quantified twice"),
+        }
+    }
+}
+
+/// One line of DIMACS, terminated by 0.
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Self::Xor(_) = self {
+            write!(f, "x")?;
+        }
+        self.literals().iter().try_for_each(|l| write!(f, "{l} "))?;
+        write!(f, "0")
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const CNF_HEADER: &str = "p cnf VARIABLES CLAUSES";
+const WCNF_HEADER: &str = "p wcnf VARIABLES CLAUSES [TOP]";
+const INCCNF_HEADER: &str = "p inccnf";
+
+/// The declared numbers of variables and clauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub variables: usize,
+    pub clauses: usize,
+}
+
+/// The header of the classic WCNF format, clauses weighing at least `top`
+/// are hard. Without `top` all clauses are soft.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightedHeader {
+    pub variables: usize,
+    pub clauses: usize,
+    pub top: Option<u64>,
+}
+
+/// A constraint in DIMACS literals, without the terminating 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    Clause(Vec<isize>),
+    /// An odd number of the literals is true, written `x1 -2 3 0`.
+    Xor(Vec<isize>),
+}
+
+/// Consecutive variables of the QDIMACS prefix under the same quantifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub universal: bool,
+    pub variables: Vec<usize>,
+}
+
+/// A line of the incremental iCNF format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Clause(Vec<isize>),
+    /// Solve under these literals, written `a 1 -2 0`.
+    Assume(Vec<isize>),
+}
+
+/// A clause of a MaxSAT problem, a soft one costs its weight if falsified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeightedClause {
+    Hard(Vec<isize>),
+    Soft(u64, Vec<isize>),
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    /// The expected start of the header.
+    MissingHeader(&'static str),
+    /// The expected form of the header.
+    InvalidHeader(&'static str),
+    NotANumber(String),
+    VariableTooLarge(isize),
+    TooManyClauses,
+    TooFewClauses,
+    UnterminatedClause,
+    NonPositiveWeight(String),
+    /// A weight, or the sum of the soft ones, exceeds `u64`.
+    WeightOverflow(String),
+    BoundTwice(usize),
+}
+
+/// Where and why the input is malformed, lines and columns count from 1.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+/// Splits lines into tokens and keeps track of their position.
+struct Lexer<R> {
+    reader: R,
+    buffer: String,
+    /// Tokens of the current line not read yet with their columns, reversed.
+    tokens: Vec<(usize, String)>,
+    line: usize,
+    column: usize,
+    /// Whether the end of the formula was reached.
+    ended: bool,
+}
+
+/// Streaming DIMACS CNF parser over any `BufRead`, yielding one constraint
+/// at a time.
+pub struct Parser<R> {
+    lexer: Lexer<R>,
+    strict: bool,
+    header: Option<Header>,
+    prefix: Vec<Block>,
+    clauses: usize,
+    done: bool,
+}
+
+/// Streaming parser of the incremental iCNF format, where clauses are
+/// interleaved with assumptions, each of them asking for a solve.
+pub struct IncrementalParser<R> {
+    lexer: Lexer<R>,
+    strict: bool,
+    header: bool,
+    done: bool,
+}
+
+/// Streaming parser of weighted MaxSAT problems, both in the classic
+/// `p wcnf` format and in the MaxSAT 2022 one without header, where hard
+/// clauses start with `h`.
+pub struct WeightedParser<R> {
+    lexer: Lexer<R>,
+    strict: bool,
+    /// `Some(None)` once the input turned out to have no header.
+    header: Option<Option<WeightedHeader>>,
+    clauses: usize,
+    total: u64,
+    done: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Solver;
+
+    /// Random constraints from a xorshift generator seeded by `seed`.
+    fn formula(seed: u64, variables: usize) -> Vec<Constraint> {
+        let mut state = seed | 1;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+        (0..next(40))
+            .map(|_| {
+                let literals = (0..next(6))
+                    .map(|_| (next(variables) + 1) as isize * [1, -1][next(2)])
+                    .collect();
+                match next(5) {
+                    0 => Constraint::Xor(literals),
+                    _ => Constraint::Clause(literals),
+                }
+            })
+            .collect()
+    }
+
+    fn parse(text: &[u8]) -> (Header, Vec<Constraint>) {
+        let mut parser = Parser::new(text, true);
+        let header = parser.header().unwrap();
+        (header, parser.collect::<Result<_, _>>().unwrap())
+    }
+
+    fn sorted(mut x: Vec<Constraint>) -> Vec<Constraint> {
+        x.sort_by(|a, b| a.literals().cmp(b.literals()));
+        x
+    }
+
+    #[test]
+    fn round_trip() {
+        for seed in 0..200 {
+            let constraints = formula(seed, 12);
+            let mut text = Vec::new();
+            write(&mut text, 12, &constraints, "c comment\n").unwrap();
+            let (header, parsed) = parse(&text);
+            assert_eq!(header.variables, 12);
+            assert_eq!(sorted(parsed), sorted(constraints.clone()));
+
+            let mut solver = Solver::new(12, constraints.len());
+            for x in constraints.iter().cloned() {
+                match x {
+                    Constraint::Clause(x) => solver.add_clause(x).unwrap(),
+                    Constraint::Xor(x) => solver.add_xor(x).unwrap(),
+                }
+            }
+            let mut text = Vec::new();
+            solver.write_input(&mut text, true).unwrap();
+            assert_eq!(sorted(parse(&text).1), sorted(constraints));
+        }
+    }
+
+    fn cnf(text: &str, strict: bool) -> Result<Vec<Constraint>, ErrorKind> {
+        let parser = Parser::new(text.as_bytes(), strict);
+        parser.collect::<Result<_, _>>().map_err(|x| x.kind)
+    }
+
+    #[test]
+    fn cnf_format() {
+        use Constraint::Clause;
+        let expected = vec![Clause(vec![1, -2]), Clause(vec![2, 3])];
+        // The trailer of the SATLIB benchmarks.
+        let satlib = "p cnf 3 2\n1 -2 0\n2 3 0\n%\n0\n\n";
+        assert_eq!(cnf(satlib, true).unwrap(), expected);
+        // Empty lines, and comments anywhere, also within a clause.
+        let spaced = "c x\n\np cnf 3 2\nc y\n\n1 -2 0\n  \n2\nc z\n3 0\n";
+        assert_eq!(cnf(spaced, true).unwrap(), expected);
+        let missing = cnf("1 -2 0\n", false);
+        assert!(matches!(missing, Err(ErrorKind::MissingHeader(_))));
+
+        // What only a strict parser rejects.
+        let few = "p cnf 3 3\n1 -2 0\n2 3 0\n";
+        assert!(matches!(cnf(few, true), Err(ErrorKind::TooFewClauses)));
+        let many = "p cnf 3 1\n1 -2 0\n2 3 0\n";
+        assert!(matches!(cnf(many, true), Err(ErrorKind::TooManyClauses)));
+        let large = "p cnf 2 2\n1 -2 0\n2 3 0\n";
+        assert!(matches!(
+            cnf(large, true),
+            Err(ErrorKind::VariableTooLarge(3))
+        ));
+        let open = "p cnf 3 2\n1 -2 0\n2 3";
+        assert!(matches!(
+            cnf(open, true),
+            Err(ErrorKind::UnterminatedClause)
+        ));
+        for text in [few, many, large, open] {
+            assert_eq!(cnf(text, false).unwrap(), expected);
+        }
+        // Garbage ends the formula only after the declared clauses.
+        let garbage = "p cnf 3 2\n1 -2 0\n2 3 0\nend 4 0\n";
+        assert!(matches!(cnf(garbage, true), Err(ErrorKind::NotANumber(_))));
+        assert_eq!(cnf(garbage, false).unwrap(), expected);
+        let inside = "p cnf 1 2\n1 0\nfoo\n-1 0\n";
+        assert!(matches!(cnf(inside, false), Err(ErrorKind::NotANumber(_))));
+        let clause = "p cnf 3 2\n1 -2 0\n2 x 3 0\n";
+        assert!(matches!(cnf(clause, false), Err(ErrorKind::NotANumber(_))));
+    }
+
+    fn weighted(text: &str) -> Result<Vec<WeightedClause>, ErrorKind> {
+        let parser = WeightedParser::new(text.as_bytes(), true);
+        parser.collect::<Result<_, _>>().map_err(|x| x.kind)
+    }
+
+    #[test]
+    fn weighted_formats() {
+        use WeightedClause::{Hard, Soft};
+        let classic = weighted("c x\np wcnf 2 3 10\n10 1 2 0\n3 -1 0\n12 -2 0\n");
+        let modern = weighted("c x\nh 1 2 0\n3 -1 0\nh -2 0\n");
+        let expected = vec![Hard(vec![1, 2]), Soft(3, vec![-1]), Hard(vec![-2])];
+        assert_eq!(classic.unwrap(), expected);
+        assert_eq!(modern.unwrap(), expected);
+
+        let zero = weighted("h 1 0\n0 -1 0\n");
+        assert!(matches!(zero, Err(ErrorKind::NonPositiveWeight(_))));
+        let negative = weighted("-3 -1 0\n");
+        assert!(matches!(negative, Err(ErrorKind::NonPositiveWeight(_))));
+        let large = weighted("18446744073709551616 1 0\n");
+        assert!(matches!(large, Err(ErrorKind::WeightOverflow(_))));
+        let sum = weighted("18446744073709551615 1 0\n1 -1 0\n");
+        assert!(matches!(sum, Err(ErrorKind::WeightOverflow(_))));
+    }
+
+    fn queries(text: &str, strict: bool) -> Result<Vec<Query>, ErrorKind> {
+        let parser = IncrementalParser::new(text.as_bytes(), strict);
+        parser.collect::<Result<_, _>>().map_err(|x| x.kind)
+    }
+
+    #[test]
+    fn incremental_format() {
+        use Query::{Assume, Clause};
+        let text = "c x\np inccnf\n1 2 0\na -1 0\n-2\n3 0\na 0\n";
+        let expected = vec![
+            Clause(vec![1, 2]),
+            Assume(vec![-1]),
+            Clause(vec![-2, 3]),
+            Assume(vec![]),
+        ];
+        assert_eq!(queries(text, true).unwrap(), expected);
+
+        let missing = queries("1 2 0\n", false);
+        assert!(matches!(missing, Err(ErrorKind::MissingHeader(_))));
+        let counts = "p inccnf 2 1\n1 0\n";
+        assert!(matches!(
+            queries(counts, true),
+            Err(ErrorKind::InvalidHeader(_))
+        ));
+        assert_eq!(queries(counts, false).unwrap(), vec![Clause(vec![1])]);
+        let open = "p inccnf\n1 0\na -1";
+        assert!(matches!(
+            queries(open, true),
+            Err(ErrorKind::UnterminatedClause)
+        ));
+        let expected = vec![Clause(vec![1]), Assume(vec![-1])];
+        assert_eq!(queries(open, false).unwrap(), expected);
+    }
+
+    fn prefix(text: &str, strict: bool) -> Result<(Vec<Block>, Vec<Constraint>), ErrorKind> {
+        let mut parser = Parser::new(text.as_bytes(), strict);
+        let prefix = parser.prefix().map_err(|x| x.kind)?.to_vec();
+        let constraints = parser.collect::<Result<_, _>>().map_err(|x| x.kind)?;
+        Ok((prefix, constraints))
+    }
+
+    #[test]
+    fn qdimacs_prefix() {
+        let block = |universal, variables: &[usize]| Block {
+            universal,
+            variables: variables.to_vec(),
+        };
+        // Consecutive blocks of the same quantifier merge.
+        let text = "c x\np cnf 4 1\na 1 0\na 2 0\ne 3\n4 0\n1 -3 0\n";
+        let (blocks, constraints) = prefix(text, true).unwrap();
+        assert_eq!(blocks, [block(true, &[1, 2]), block(false, &[3, 4])]);
+        assert_eq!(constraints, [Constraint::Clause(vec![1, -3])]);
+        assert!(prefix("p cnf 1 1\n1 0\n", true).unwrap().0.is_empty());
+
+        let twice = "p cnf 2 1\ne 1 0\na 2 1 0\n1 2 0\n";
+        assert!(matches!(prefix(twice, true), Err(ErrorKind::BoundTwice(1))));
+        let (blocks, _) = prefix(twice, false).unwrap();
+        assert_eq!(blocks, [block(false, &[1]), block(true, &[2, 1])]);
+        let large = "p cnf 2 1\ne 3 0\n1 2 0\n";
+        assert!(matches!(
+            prefix(large, true),
+            Err(ErrorKind::VariableTooLarge(3))
+        ));
+    }
+}
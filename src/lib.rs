@@ -1,12 +1,16 @@
-#![feature(
-    allocator_api,
-    alloc_layout_extra,
-    iter_chain,
-    ptr_metadata,
-    vec_push_within_capacity
-)]
+#![feature(allocator_api, ptr_metadata)]
 
 pub mod alloc;
 pub mod bits;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod circuit;
+pub mod dimacs;
+pub mod limits;
+pub mod log;
+pub mod preprocessing;
 pub mod problem;
+pub mod reconstruction;
 pub mod solver;
+pub mod statistics;
+pub mod terminator;
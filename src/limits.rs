@@ -0,0 +1,108 @@
+use crate::alloc::StacklikeAlloc;
+use crate::statistics::Statistics;
+use crate::terminator::Terminator;
+
+use std::time::{Duration, Instant};
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(
+        limits: Limits,
+        arena: &'a StacklikeAlloc,
+        terminator: &'a Terminator,
+        callback: Option<&'a Callback>,
+    ) -> Self {
+        Self {
+            limits,
+            arena,
+            terminator,
+            callback,
+            start: Instant::now(),
+            inprocessing: false,
+            stats: Statistics::default(),
+        }
+    }
+
+    /// Account for a newly entered recursion node at `depth`.
+    pub(crate) fn node(&mut self, depth: usize) -> Result<(), Reason> {
+        self.stats.nodes += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        exceeds(self.stats.nodes, self.limits.nodes, Reason::Nodes)?;
+        self.check()
+    }
+
+    /// Check the limits that do not count events.
+    pub(crate) fn check(&self) -> Result<(), Reason> {
+        if self.terminator.is_terminated() || self.callback.is_some_and(|f| f()) {
+            return Err(Reason::Terminated);
+        }
+
+        if let Some(x) = self.limits.time
+            && self.start.elapsed() >= x
+        {
+            return Err(Reason::Time);
+        }
+
+        exceeds(self.arena.used(), self.limits.memory, Reason::Memory)
+    }
+
+    pub(crate) fn decision(&mut self) -> Result<(), Reason> {
+        self.stats.decisions += 1;
+        exceeds(
+            self.stats.decisions,
+            self.limits.decisions,
+            Reason::Decisions,
+        )
+    }
+
+    pub(crate) fn conflict(&mut self) -> Result<(), Reason> {
+        self.stats.conflicts += 1;
+        exceeds(
+            self.stats.conflicts,
+            self.limits.conflicts,
+            Reason::Conflicts,
+        )
+    }
+}
+
+fn exceeds<T: PartialOrd>(value: T, limit: Option<T>, reason: Reason) -> Result<(), Reason> {
+    match limit {
+        Some(x) if value > x => Err(reason),
+        _ => Ok(()),
+    }
+}
+
+/// Polled during search, returning `true` stops it like `Terminator::terminate`.
+pub type Callback = dyn Fn() -> bool + Send;
+
+/// Upper bounds for a single call to `Solver::solve`, `None` means unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub decisions: Option<u64>,
+    pub conflicts: Option<u64>,
+    pub nodes: Option<u64>,
+    /// Bytes in use by the solver's arena.
+    pub memory: Option<usize>,
+}
+
+/// The limit that stopped the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Time,
+    Decisions,
+    Conflicts,
+    Nodes,
+    Memory,
+    Terminated,
+}
+
+pub(crate) struct Budget<'a> {
+    limits: Limits,
+    arena: &'a StacklikeAlloc,
+    terminator: &'a Terminator,
+    callback: Option<&'a Callback>,
+    start: Instant,
+    /// Simplify during the search, see `Preprocessing::inprocessing`.
+    pub(crate) inprocessing: bool,
+    pub(crate) stats: Statistics,
+}
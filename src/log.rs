@@ -0,0 +1,60 @@
+use core::fmt::Arguments;
+
+/// Emit a message through a `Logger`, formatting only if it is enabled.
+macro_rules! log {
+    ($logger:expr, $level:expr, $($arg:tt)*) => {
+        if $logger.enabled($level) {
+            $logger.log($level, format_args!($($arg)*));
+        }
+    };
+}
+
+pub(crate) use log;
+
+impl Logger {
+    pub(crate) fn enabled(&self, level: Verbosity) -> bool {
+        level != Verbosity::Quiet && level <= self.verbosity && self.sink.is_some()
+    }
+
+    pub(crate) fn log(&mut self, level: Verbosity, message: Arguments<'_>) {
+        if let Some(x) = &mut self.sink {
+            x.log(level, message);
+        }
+    }
+
+    pub(crate) fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub(crate) fn set_sink(&mut self, sink: Option<Box<dyn Sink>>) {
+        self.sink = sink;
+    }
+}
+
+impl<F: FnMut(Verbosity, Arguments<'_>) + Send> Sink for F {
+    fn log(&mut self, level: Verbosity, message: Arguments<'_>) {
+        self(level, message)
+    }
+}
+
+/// Receives every message up to the configured verbosity.
+pub trait Sink: Send {
+    fn log(&mut self, level: Verbosity, message: Arguments<'_>);
+}
+
+/// Ordered from silent to chatty, a message is emitted if its level is at
+/// most the configured one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    #[default]
+    Quiet,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Default)]
+pub(crate) struct Logger {
+    verbosity: Verbosity,
+    sink: Option<Box<dyn Sink>>,
+}
@@ -1,10 +1,16 @@
-use sat_solver::solver::Solver;
-use std::io;
-use std::time::Instant;
+#![allow(clippy::unit_arg)]
+
+use sat_solver::dimacs::{Constraint, IncrementalParser, Parser, Query};
+use sat_solver::limits::Limits;
+use sat_solver::log::Verbosity;
+use sat_solver::reconstruction::Reconstruction;
+use sat_solver::solver::{Solution, Solver};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use std::{fs, io};
 
 fn parse_numbers(line: &str) -> Result<Vec<isize>, String> {
-    line.trim()
-        .split_whitespace()
+    line.split_whitespace()
         .map(|x| match str::parse::<isize>(x) {
             Ok(x) => Ok(x),
             _ => Err(x.to_string()),
@@ -12,97 +18,301 @@ fn parse_numbers(line: &str) -> Result<Vec<isize>, String> {
         .collect()
 }
 
-enum HeaderParseError {
-    MissingHeader,
-    NegativeNumber(isize),
-    NotANumber(String),
-}
+fn parse_options<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut limits = Limits::default();
+    let mut verbosity = Verbosity::Quiet;
+    let (mut output, mut reconstruction, mut extend) = (None, None, None);
+    let (mut gates, mut strict, mut incremental) = (false, false, false);
+    for arg in args {
+        let level = match arg.as_str() {
+            "-v" => Some(Verbosity::Info),
+            "-vv" => Some(Verbosity::Debug),
+            "-vvv" => Some(Verbosity::Trace),
+            _ => None,
+        };
+        if let Some(x) = level {
+            verbosity = x;
+            continue;
+        }
+        match arg.as_str() {
+            "--gates" => gates = true,
+            "--strict" => strict = true,
+            "--incremental" => incremental = true,
+            _ => (),
+        }
+        if matches!(arg.as_str(), "--gates" | "--strict" | "--incremental") {
+            continue;
+        }
 
-fn parse_header(line: String) -> Result<Header, HeaderParseError> {
-    if !line.starts_with("p cnf") {
-        return Err(HeaderParseError::MissingHeader);
-    }
-    let nums = match parse_numbers(&line["p cnf".len()..]) {
-        Ok(x) => x,
-        Err(x) => return Err(HeaderParseError::NotANumber(x)),
-    };
+        let (key, value) = arg.split_once('=').ok_or(arg.clone())?;
+        let path = match key {
+            "--preprocess" => Some(&mut output),
+            "--reconstruction" => Some(&mut reconstruction),
+            "--extend" => Some(&mut extend),
+            _ => None,
+        };
+        if let Some(x) = path {
+            *x = Some(value.to_string());
+            continue;
+        }
 
-    if let Some(&x) = nums.iter().find(|&&x| x < 0) {
-        return Err(HeaderParseError::NegativeNumber(x));
+        let value = str::parse::<u64>(value).map_err(|_| arg.clone())?;
+        match key {
+            "--time" => limits.time = Some(Duration::from_secs(value)),
+            "--decisions" => limits.decisions = Some(value),
+            "--conflicts" => limits.conflicts = Some(value),
+            "--nodes" => limits.nodes = Some(value),
+            "--memory" => limits.memory = Some((value as usize) << 20),
+            _ => return Err(arg),
+        }
     }
-
-    Ok(Header {
-        vrs: nums[0] as usize,
-        cls: nums[1] as usize,
+    let mode = match (output, reconstruction, extend) {
+        (None, None, None) if gates && incremental => return Err("--gates".to_string()),
+        (None, None, None) if gates => Mode::Gates,
+        (None, None, None) if incremental => Mode::Incremental,
+        (None, None, None) => Mode::Solve,
+        _ if incremental => return Err("--incremental".to_string()),
+        _ if gates => return Err("--gates".to_string()),
+        (Some(output), Some(reconstruction), None) => Mode::Preprocess {
+            output,
+            reconstruction,
+        },
+        (None, None, Some(x)) => Mode::Extend(x),
+        (_, _, Some(_)) => return Err("--extend".to_string()),
+        (None, _, None) => return Err("--reconstruction".to_string()),
+        _ => return Err("--preprocess".to_string()),
+    };
+    Ok(Options {
+        limits,
+        verbosity,
+        strict,
+        mode,
     })
 }
 
-fn main() -> io::Result<()> {
-    println!("Enter satisfiability problem in DIMACS format.");
-    println!("Press Ctrl-D to quit.");
-    let mut h = None;
+/// Extend the model on stdin to one of the original formula.
+fn extend(path: &str) -> io::Result<()> {
+    let rec = match fs::read_to_string(path)?.parse::<Reconstruction>() {
+        Ok(x) => x,
+        Err(x) => return Ok(println!("Reconstruction is malformed: {x}.")),
+    };
+
+    let mut literals = Vec::new();
     for line in io::stdin().lines() {
         let e = line?;
-        if e.starts_with("c") {
+        if e.starts_with('c') || e.starts_with('s') {
             continue;
         }
-        match parse_header(e) {
-            Ok(x) => {
-                h = Some(x);
-                break;
-            }
-            Err(HeaderParseError::MissingHeader) => {
-                return Ok(println!("Input must start with a header."));
-            }
-            Err(HeaderParseError::NegativeNumber(x)) => {
-                return Ok(println!(
-                    "Number({x}) of variables and clauses must not be negative."
-                ));
-            }
-            Err(HeaderParseError::NotANumber(x)) => {
-                return Ok(println!("Input of '{}' is no integer.", x));
-            }
+        match parse_numbers(e.trim_start_matches('v')) {
+            Ok(v) => literals.extend(v.into_iter().filter(|&x| x != 0)),
+            Err(x) => return Ok(println!("Input of '{}' is no integer.", x)),
         }
     }
 
-    if let Some(header) = h {
-        let mut solver = Solver::new(header.vrs, header.cls);
-
-        while solver.need_to_add() {
-            let mut literals = Vec::new();
-            for line in io::stdin().lines() {
-                let e = line?;
-                if e.is_empty() {
-                    return Ok(println!("Empty lines are disallowed."));
-                }
-                if let Ok(mut v) = parse_numbers(&e) {
-                    if v[v.len() - 1] == 0 {
-                        v.pop();
-                        literals.extend(v);
-                        break;
-                    } else {
-                        literals.extend(v);
-                    }
-                } else {
-                    return Ok(println!("Incorrect clause formulation."));
-                }
-            }
-
-            match solver.add_clause(literals) {
-                Err(x) => return Ok(println!("{:?}", x)),
-                _ => (),
-            }
+    let vrs = literals.iter().map(|x| x.unsigned_abs()).max().unwrap_or(0);
+    let vrs = vrs.max(rec.variables()).max(rec.original().unwrap_or(0));
+    let mut model: Vec<isize> = (1..=vrs as isize).map(|x| -x).collect();
+    for l in literals {
+        model[l.unsigned_abs() - 1] = l;
+    }
+    rec.extend(&mut model);
+    // Without the variables added by the preprocessing.
+    model.truncate(rec.original().unwrap_or(vrs));
+    Ok(answer(&Solution::Satisfiable(model)))
+}
+
+/// Print the answer of one query in the format of the SAT competition.
+fn answer(solution: &Solution) {
+    match solution {
+        Solution::Satisfiable(model) => {
+            let mut out = String::new();
+            model.iter().for_each(|l| write!(out, " {l}").unwrap());
+            println!("s SATISFIABLE");
+            println!("v{out} 0");
         }
-        let start = Instant::now();
-        println!("Solving problem...");
-        println!("Solution is {:?}", solver.solve());
-        println!("Time spent is {}ms", start.elapsed().as_millis());
-        return Ok(println!("Bye."));
+        Solution::Unsatisfiable => println!("s UNSATISFIABLE"),
+        Solution::Unknown(_) => println!("s UNKNOWN"),
     }
-    Ok(println!("Abort? Ok..."))
 }
 
-struct Header {
-    vrs: usize,
-    cls: usize,
+/// Solve each query of the iCNF formula on stdin under its assumptions as
+/// soon as it is read. Like the IPASIR interface, every query solves the
+/// clauses so far from scratch.
+fn incremental(options: &Options) -> io::Result<()> {
+    // One solver answers all queries and grows with the clauses.
+    let mut solver = Solver::new(0, 0);
+    solver.set_limits(options.limits);
+    solver.set_verbosity(options.verbosity);
+    solver.set_sink(Some(Box::new(|_, x: std::fmt::Arguments<'_>| {
+        println!("c {x}")
+    })));
+
+    let (mut vrs, mut clauses) = (0, 0);
+    for x in IncrementalParser::new(io::stdin().lock(), options.strict) {
+        let x = match x {
+            Ok(x) => x,
+            Err(x) => return Ok(println!("c Input is malformed at {x}.")),
+        };
+        let (Query::Clause(literals) | Query::Assume(literals)) = &x;
+        vrs = literals
+            .iter()
+            .map(|l| l.unsigned_abs())
+            .fold(vrs, usize::max);
+        clauses += matches!(x, Query::Clause(_)) as usize;
+
+        let result = solver.reserve(vrs, clauses).and_then(|_| match x {
+            Query::Clause(x) => solver.add_clause(x).map(|_| None),
+            Query::Assume(x) => solver.solve_assuming(&x).map(Some),
+        });
+        match result {
+            Ok(Some(x)) => answer(&x),
+            Ok(None) => {}
+            Err(x) => return Ok(println!("c {:?}", x)),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(x) => x,
+        Err(x) => {
+            return Ok(println!(
+                "Option '{x}' is unknown or misused, use -v, -vv, -vvv, --time=SECS, --decisions=N, --conflicts=N, --nodes=N, --memory=MB, --preprocess=FILE with --reconstruction=FILE, --extend=FILE, --gates, --incremental or --strict."
+            ));
+        }
+    };
+    if let Mode::Extend(x) = &options.mode {
+        return extend(x);
+    }
+    if let Mode::Incremental = options.mode {
+        return incremental(&options);
+    }
+
+    println!("Enter satisfiability problem in (Q)DIMACS format.");
+    println!("Press Ctrl-D to quit.");
+    let mut parser = Parser::new(io::stdin().lock(), options.strict);
+    let header = match parser.header() {
+        Ok(x) => x,
+        Err(x) => return Ok(println!("Input is malformed at {x}.")),
+    };
+    // QDIMACS with a universal quantifier is solved as QBF.
+    let prefix = parser.prefix().map_or(Vec::new(), |x| x.to_vec());
+    let qbf = prefix.iter().any(|x| x.universal);
+    let constraints = match parser.collect::<Result<Vec<_>, _>>() {
+        Ok(x) => x,
+        Err(x) => return Ok(println!("Input is malformed at {x}.")),
+    };
+    // Unless strict, variables may exceed the header.
+    let vrs = constraints.iter().flat_map(|x| x.literals());
+    let vrs = vrs
+        .map(|l| l.unsigned_abs())
+        .fold(header.variables, usize::max);
+
+    let mut solver = Solver::new(vrs, constraints.len());
+    solver.set_limits(options.limits);
+    solver.set_verbosity(options.verbosity);
+    solver.set_sink(Some(Box::new(|_, x: std::fmt::Arguments<'_>| {
+        println!("c {x}")
+    })));
+
+    for x in constraints {
+        let result = match x {
+            Constraint::Clause(x) => solver.add_clause(x),
+            Constraint::Xor(x) => solver.add_xor(x),
+        };
+        if let Err(x) = result {
+            return Ok(println!("{:?}", x));
+        }
+    }
+    if let Mode::Gates = options.mode {
+        let circuit = match solver.circuit() {
+            Ok(x) => x,
+            Err(x) => return Ok(println!("{:?}", x)),
+        };
+        print!("{circuit}");
+        return Ok(println!("Found {} gates.", circuit.len()));
+    }
+    if let Mode::Preprocess {
+        output,
+        reconstruction,
+    } = &options.mode
+    {
+        if qbf {
+            return Ok(println!(
+                "Preprocessing does not support universal quantifiers."
+            ));
+        }
+        let clauses = match solver.preprocess() {
+            Ok(x) => x,
+            Err(x) => return Ok(println!("{:?}", x)),
+        };
+        if let Err(x) = solver.write_simplified(&mut fs::File::create(output)?, true) {
+            return Ok(println!("{:?}", x));
+        }
+        fs::write(reconstruction, solver.reconstruction().to_string())?;
+        println!("{}", solver.statistics());
+        return Ok(println!("Wrote {} clauses to {output}.", clauses.len()));
+    }
+
+    let start = Instant::now();
+    println!("Solving problem...");
+    let solution = match qbf {
+        true => solver.solve_qbf(&prefix),
+        false => solver.solve(),
+    };
+    println!("Solution is {:?}", solution);
+    println!("Time spent is {}ms", start.elapsed().as_millis());
+    println!("{}", solver.statistics());
+    Ok(println!("Bye."))
+}
+
+struct Options {
+    limits: Limits,
+    verbosity: Verbosity,
+    /// Reject what the lenient DIMACS parser lets pass.
+    strict: bool,
+    mode: Mode,
+}
+
+enum Mode {
+    Solve,
+    /// Write the simplified formula and its reconstruction.
+    Preprocess {
+        output: String,
+        reconstruction: String,
+    },
+    /// Extend a model of a simplified formula with the given reconstruction.
+    Extend(String),
+    /// Print the gates defined by the clauses.
+    Gates,
+    /// Answer each query of an iCNF formula.
+    Incremental,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn misused_options() {
+        let x = mode(&["--preprocess=a.cnf", "--reconstruction=a.rec"]);
+        assert!(matches!(x.map(|x| x.mode), Ok(Mode::Preprocess { .. })));
+        let x = mode(&["--reconstruction=a.rec"]);
+        assert_eq!(x.err().unwrap(), "--reconstruction");
+        let x = mode(&["--preprocess=a.cnf"]);
+        assert_eq!(x.err().unwrap(), "--preprocess");
+        let x = mode(&["--extend=a.rec", "--reconstruction=a.rec"]);
+        assert_eq!(x.err().unwrap(), "--extend");
+        let x = mode(&["--extend=a.rec", "--gates"]);
+        assert_eq!(x.err().unwrap(), "--gates");
+        let x = mode(&["--extend=a.rec", "--incremental"]);
+        assert_eq!(x.err().unwrap(), "--incremental");
+        let x = mode(&["--nodes=x"]);
+        assert_eq!(x.err().unwrap(), "--nodes=x");
+    }
 }
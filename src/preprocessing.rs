@@ -0,0 +1,46 @@
+/// Optional simplifications run by `Solver::solve` before the search.
+#[derive(Debug, Clone, Copy)]
+pub struct Preprocessing {
+    /// Gauss-Jordan elimination on the XOR constraints encoded in the
+    /// clauses, those left are kept as rows next to the clauses and
+    /// eliminated again at every decision level of the search.
+    pub xor: bool,
+    /// Equivalent literal substitution.
+    pub equivalences: bool,
+    /// Blocked clause elimination.
+    pub blocked: bool,
+    /// Removal of clauses satisfied by autarkies.
+    pub autarky: bool,
+    /// Clause vivification.
+    pub vivification: bool,
+    /// Vivify again every few decision levels of the search.
+    pub inprocessing: bool,
+    /// Failed literal probing.
+    pub probing: bool,
+    /// Bounded variable elimination.
+    pub elimination: bool,
+    /// Bounded variable addition, which hides its fresh variables.
+    pub addition: bool,
+    /// Lex-leader clauses for symmetries found right before the search.
+    pub symmetry: bool,
+    /// Replace clauses spelling out at-most-k constraints by native ones.
+    pub cardinality: bool,
+}
+
+impl Default for Preprocessing {
+    fn default() -> Self {
+        Self {
+            xor: true,
+            equivalences: true,
+            blocked: true,
+            autarky: true,
+            vivification: true,
+            inprocessing: false,
+            probing: true,
+            elimination: true,
+            addition: true,
+            cardinality: true,
+            symmetry: true,
+        }
+    }
+}
@@ -1,15 +1,39 @@
 use crate::bits::bit_matrix::BitMatrix;
 use crate::bits::bit_tools::{BITS, Bits, indices, iter_ones_slice_usize};
+use crate::dimacs::{self, Constraint};
+use crate::limits::{Budget, Reason};
+use crate::reconstruction::Reconstruction;
+use card::AtMost;
+pub(crate) use qbf::Prefix;
+use xor::Xor;
 
 use core::alloc::Allocator;
 use core::iter::{Map, Zip, repeat_n, zip};
 use core::ops::{BitAnd, BitAndAssign, BitOrAssign};
-
+use std::io::{self, Write};
+
+mod autarky;
+mod bva;
+mod card;
+mod component;
+mod elim;
+mod equiv;
+mod fragment;
+mod gates;
+mod probe;
+mod propagate;
+mod qbf;
+mod subsume;
+mod symmetry;
+mod vivify;
+mod xor;
+
+#[allow(clippy::type_complexity)]
 fn zip_with<I, J, F, T, S, R>(
     lhs: I,
     rhs: J,
     f: F,
-) -> Map<Zip<<I as IntoIterator>::IntoIter, <J as IntoIterator>::IntoIter>, impl (FnMut((T, S)) -> R)>
+) -> Map<Zip<<I as IntoIterator>::IntoIter, <J as IntoIterator>::IntoIter>, impl FnMut((T, S)) -> R>
 where
     I: IntoIterator<Item = T>,
     J: IntoIterator<Item = S>,
@@ -18,6 +42,19 @@ where
     zip(lhs, rhs).map(move |(x, y)| f(x, y))
 }
 
+/// Swap the bits of each literal with the one of its negation.
+const fn swap_pairs(x: usize) -> usize {
+    const EVEN: usize = usize::MAX / 3;
+    ((x & EVEN) << 1) | ((x >> 1) & EVEN)
+}
+
+/// Literals with more candidate resolvents are not checked for blocked clauses.
+const BLOCKED_BOUND: usize = 1 << 12;
+
+/// Decision levels between two rounds of inprocessing.
+const INPROCESSING_INTERVAL: usize = 8;
+
+#[allow(clippy::unused_unit)]
 fn zip_for_each<I, J, F, T, S>(lhs: I, rhs: J, f: F)
 where
     I: IntoIterator<Item = T>,
@@ -29,11 +66,21 @@ where
 
 impl<A: Allocator + Copy> Problem<A> {
     pub(crate) fn new_in(a: A) -> Self {
-        Self(BitMatrix::new_in(a))
+        Self(
+            BitMatrix::new_in(a),
+            Vec::new_in(a),
+            AtMost::new_in(a),
+            Xor::new_in(a),
+        )
     }
 
     pub(crate) fn with_capacity_in(clauses: usize, variables: usize, a: A) -> Self {
-        Self(BitMatrix::with_capacity_in(clauses, variables << 1, a))
+        Self(
+            BitMatrix::with_capacity_in(clauses, variables << 1, a),
+            Vec::with_capacity_in(variables, a),
+            AtMost::new_in(a),
+            Xor::new_in(a),
+        )
     }
 
     pub(crate) const fn clauses(&self) -> usize {
@@ -55,21 +102,113 @@ impl<A: Allocator + Copy> Problem<A> {
         let row = self.0.rows();
         self.0.push_empty_row();
         for l in literals {
-            let i = (l.abs() as usize) << 1;
+            let i = l.unsigned_abs() << 1;
             let j = l.is_negative() as usize;
 
-            let k = i + 1 - 2;
-            while k >= self.0.cols() {
-                self.0.push_empty_col(); // Quick'n'dirty
-            }
+            self.reserve(l.unsigned_abs());
             self.0.set(row, i + j - 2);
         }
     }
 
+    /// Push the columns of a new variable numbered after `vars`, which counts
+    /// it. Returns its positive column.
+    pub(crate) fn fresh_variable(&mut self, vars: &mut usize) -> usize {
+        *vars += 1;
+        for _ in 0..2 {
+            self.0.push_empty_col();
+            self.2.push_empty_col();
+            self.3.push_empty_col();
+        }
+        self.1.push(*vars);
+        self.literals() - 2
+    }
+
+    /// Push columns up to DIMACS variable `var`.
+    fn reserve(&mut self, var: usize) {
+        while (var << 1) > self.0.cols() {
+            self.0.push_empty_col(); // Quick'n'dirty
+            self.2.push_empty_col();
+            self.3.push_empty_col();
+            if self.0.cols() & 1 == 0 {
+                self.1.push(self.1.len() + 1);
+            }
+        }
+    }
+
+    /// Push a clause given by the columns of its literals.
+    fn add_row<I: Iterator<Item = usize>>(&mut self, literals: I) {
+        let row = self.0.rows();
+        self.0.push_empty_row();
+        literals.for_each(|l| self.0.set(row, l));
+    }
+
+    /// The DIMACS literals of clause `clause`.
+    fn clause(&self, clause: usize) -> Vec<isize> {
+        iter_ones_slice_usize(self.0.row_data(clause))
+            .map(|l| self.literal(l))
+            .collect()
+    }
+
+    /// The DIMACS literals of all clauses, at-most constraints included.
+    pub(crate) fn to_vec(&self) -> Vec<Vec<isize>> {
+        let mut res: Vec<_> = (0..self.0.rows()).map(|i| self.clause(i)).collect();
+        res.extend(self.at_most_clauses());
+        res
+    }
+
+    /// Write the clauses in DIMACS CNF, see `to_vec`.
+    pub(crate) fn write_dimacs<W: Write>(
+        &self,
+        out: &mut W,
+        variables: usize,
+        comments: &str,
+    ) -> io::Result<()> {
+        let clauses: Vec<_> = self.to_vec().into_iter().map(Constraint::Clause).collect();
+        let vars = clauses.iter().flat_map(|x| x.literals());
+        let vars = vars.map(|l| l.unsigned_abs()).fold(variables, usize::max);
+        dimacs::write(out, vars, &clauses, comments)
+    }
+
+    /// Leave the empty clause only.
+    fn set_unsatisfiable(&mut self) {
+        while self.0.rows() > 0 {
+            self.del_clause(self.0.rows() - 1);
+        }
+        self.2.clear();
+        self.3.clear();
+        self.add_row(core::iter::empty());
+    }
+
     pub(crate) fn del_clause(&mut self, clause: usize) {
         self.0.swap_remove_row(clause);
     }
 
+    /// Remove both literal columns of the variable of `literal`. XOR rows
+    /// still over it are dropped, its value was not substituted into them.
+    fn del_variable(&mut self, literal: usize) {
+        let i = literal & !1;
+        self.0.swap_remove_col(i + 1);
+        self.0.swap_remove_col(i);
+        self.2.swap_remove_col(i + 1);
+        self.2.swap_remove_col(i);
+        self.3.forget(i);
+        self.3.swap_remove_col(i + 1);
+        self.3.swap_remove_col(i);
+        self.1.swap_remove(i >> 1);
+    }
+
+    /// The DIMACS literal of column `literal`.
+    fn literal(&self, literal: usize) -> isize {
+        let x = self.1[literal >> 1] as isize;
+        if literal & 1 == 0 { x } else { -x }
+    }
+
+    /// The column of DIMACS literal `literal`, if its variable is left.
+    fn column(&self, literal: isize) -> Option<usize> {
+        let i = self.1.iter().position(|&x| x == literal.unsigned_abs())?;
+        Some((i << 1) | literal.is_negative() as usize)
+    }
+
     const fn allocator(&self) -> &A {
         self.0.allocator()
     }
@@ -79,7 +218,7 @@ impl<A: Allocator + Copy> Problem<A> {
     }
 
     /// Remove all clauses, s.t. ∀i,j : lᵢ ∈ Cⱼ ⇒ (lᵢ) ∉ Cⱼ.
-    fn remove_tautologies(&mut self) {
+    fn remove_tautologies(&mut self, budget: &mut Budget) {
         let mut to_delete = self.buffer();
         to_delete.extend(repeat_n(0, self.0.integers_used_each_col()));
 
@@ -93,37 +232,99 @@ impl<A: Allocator + Copy> Problem<A> {
 
         let mut tmp = self.buffer();
         tmp.extend(iter_ones_slice_usize(&to_delete));
+        budget.stats.tautologies += tmp.len() as u64;
         tmp.into_iter().rev().for_each(|i| self.del_clause(i));
     }
 
-    /// Remove (and resolve) pure literals.
-    fn remove_pure_literals(&mut self) {
-        let mut tmp = self.buffer();
+    /// Clauses blocked on `literal`, s.t. every resolvent on it is a tautology.
+    /// If `literal` is pure, these are all clauses containing it. At-most
+    /// constraints over `literal` stand for clauses with its negation.
+    fn blocked_rows(&self, literal: usize) -> Vec<usize, A> {
+        let mut res = self.buffer();
+        if self.2.contains(literal) {
+            return res;
+        }
+        let mut partners = self.buffer();
+        partners.extend(iter_ones_slice_usize(self.0.col_data(literal ^ 1)));
+        if partners.is_empty() {
+            res.extend(iter_ones_slice_usize(self.0.col_data(literal)));
+            return res;
+        }
+
+        let (i, j) = indices(literal);
+        let mut negated = self.buffer();
+        for c in iter_ones_slice_usize(self.0.col_data(literal)) {
+            negated.extend(self.0.row_data(c).iter().map(|&x| swap_pairs(x)));
+            negated[i] &= !(3 << (j & !1));
+            if partners
+                .iter()
+                .all(|&d| zip_with(&negated, self.0.row_data(d), |x, y| x & y).any(|x| x != 0))
+            {
+                res.push(c);
+            }
+            negated.clear();
+        }
+        res
+    }
+
+    /// The literal of the variable at columns `i` and `i + 1` whose negation
+    /// occurs nowhere, if any.
+    fn pure(&self, i: usize) -> Option<usize> {
+        [i + 1, i]
+            .into_iter()
+            .find(|&l| self.0.col_data(l ^ 1).iter().all(|&x| x == 0) && !self.2.contains(l))
+    }
 
+    /// Remove (and resolve) pure literals, their values are pushed onto `trail`.
+    fn remove_pure_literals(&mut self, budget: &mut Budget, trail: &mut Vec<isize>) {
         let mut i = 0;
         while i < self.literals() {
-            let pos_data = self.0.col_data(i);
-            let neg_data = self.0.col_data(i + 1);
-            if pos_data.iter().all(|&x| x == 0) {
-                tmp.extend(iter_ones_slice_usize(neg_data));
-                tmp.iter().rev().for_each(|&i| self.del_clause(i));
-                self.0.swap_remove_col(i + 1);
-                self.0.swap_remove_col(i);
-                tmp.clear();
-            } else if neg_data.iter().all(|&x| x == 0) {
-                tmp.extend(iter_ones_slice_usize(pos_data));
+            if let Some(l) = self.pure(i) {
+                let tmp = self.blocked_rows(l);
                 tmp.iter().rev().for_each(|&i| self.del_clause(i));
-                self.0.swap_remove_col(i + 1);
-                self.0.swap_remove_col(i);
-                tmp.clear();
+                trail.push(self.literal(l));
+                self.3.assign(l);
+                self.del_variable(i);
+                budget.stats.pure_literals += 1;
             } else {
                 i += 2;
             }
         }
     }
 
+    /// Remove blocked clauses, pure literals included, until fixpoint.
+    pub(crate) fn remove_blocked_clauses(
+        &mut self,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> Result<(), Reason> {
+        loop {
+            let mut changed = false;
+            for l in 0..self.literals() {
+                budget.check()?;
+                let occurrences = |l| self.0.col_data(l).iter().map(|x| x.count_ones() as usize);
+                if occurrences(l).sum::<usize>() * occurrences(l ^ 1).sum::<usize>() > BLOCKED_BOUND
+                {
+                    continue;
+                }
+
+                let tmp = self.blocked_rows(l);
+                for &i in &tmp {
+                    rec.push(self.literal(l), self.clause(i));
+                }
+                tmp.iter().rev().for_each(|&i| self.del_clause(i));
+                budget.stats.blocked += tmp.len() as u64;
+                changed |= !tmp.is_empty();
+            }
+
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
     /// Shrink clause *i*, s.t. ∀j : Cⱼ ∖ Cᵢ = {l} ⇒ (-l) ∉ Cᵢ.
-    fn shrink_clause(&mut self, clause: usize) {
+    fn shrink_clause(&mut self, clause: usize, budget: &mut Budget) {
         let (row_count, col_count) = (self.0.rows(), self.0.cols());
         assert!(clause <= row_count);
         if col_count == 0 {
@@ -179,6 +380,7 @@ impl<A: Allocator + Copy> Problem<A> {
 
             if let Some(x) = literal_to_delete {
                 self.0.flip(clause, x);
+                budget.stats.strengthened += 1;
                 tmp_row.clear();
                 continue;
             }
@@ -186,9 +388,16 @@ impl<A: Allocator + Copy> Problem<A> {
         }
     }
 
-    fn handle_shrinked<B: Allocator>(&mut self, mut shrinked: Vec<usize, B>) {
+    fn handle_shrinked<B: Allocator>(
+        &mut self,
+        mut shrinked: Vec<usize, B>,
+        budget: &mut Budget,
+    ) -> Result<(), Reason> {
         let row_count = self.0.rows();
         assert!(shrinked.iter().all(|&i| i < row_count));
+        if shrinked.is_empty() {
+            return Ok(());
+        }
 
         let (used_each_col, last_col, mask_col) = {
             let (i, j) = indices(row_count - 1);
@@ -205,8 +414,9 @@ impl<A: Allocator + Copy> Problem<A> {
             if to_delete.read(x) {
                 continue;
             }
+            budget.check()?;
 
-            self.shrink_clause(x);
+            self.shrink_clause(x, budget);
             let row = self.0.row_data(x);
 
             // Subsumption ellminiation (mark only).
@@ -222,6 +432,9 @@ impl<A: Allocator + Copy> Problem<A> {
                     );
                 }
                 tmp_col.flip(x);
+                budget.stats.subsumed += zip_with(&tmp_col, &to_delete, |x, y| x & !y)
+                    .map(|x| x.count_ones() as u64)
+                    .sum::<u64>();
                 zip_for_each(to_delete.iter_mut(), &tmp_col, BitOrAssign::bitor_assign);
                 tmp_col.clear();
             }
@@ -239,17 +452,17 @@ impl<A: Allocator + Copy> Problem<A> {
                         tmp_col.extend(repeat_n(usize::MAX, used_each_col));
                         tmp_col[last_col] &= mask_col;
 
-                        for i in iter_ones_slice_usize(&cpy_row) {
+                        for i in iter_ones_slice_usize(&tmp_row) {
                             zip_for_each(
                                 tmp_col.iter_mut(),
                                 self.0.col_data(i),
                                 BitAndAssign::bitand_assign,
                             );
                         }
-                        tmp_col.flip(x);
                         for i in iter_ones_slice_usize(&tmp_col) {
                             self.0.flip(i, l ^ 1);
                             shrinked.push(i);
+                            budget.stats.strengthened += 1;
                         }
                         tmp_col.clear();
                     }
@@ -261,14 +474,21 @@ impl<A: Allocator + Copy> Problem<A> {
                 tmp_row.clear();
             }
         }
+        Ok(())
     }
 
     /// Returns the literals with highest occurance.
     fn choose(&self) -> Option<usize> {
+        self.choose_in(|_| true)
+    }
+
+    /// Returns the literal with highest occurance among those satisfying `f`.
+    fn choose_in<F: Fn(usize) -> bool>(&self, f: F) -> Option<usize> {
         let mut max = None;
-        for i in 0..self.0.cols() {
+        for i in (0..self.0.cols()).filter(|&i| f(i)) {
             let x: u32 = self.0.col_data(i).iter().map(|x| x.count_ones()).sum();
-            debug_assert!(x > 0);
+            let x = x + self.2.count(i);
+            debug_assert!(x > 0 || self.2.contains(i ^ 1));
             match max {
                 Some((_, y)) if y >= x => (),
                 _ => max = Some((i, x)),
@@ -279,7 +499,7 @@ impl<A: Allocator + Copy> Problem<A> {
     }
 
     /// Resolve a literal and restore invariants afterwards.
-    fn resolve(&mut self, literal: usize) {
+    fn resolve(&mut self, literal: usize, budget: &mut Budget) -> Result<(), Reason> {
         let mut tmp = self.buffer();
 
         tmp.extend(iter_ones_slice_usize(self.0.col_data(literal)));
@@ -289,45 +509,110 @@ impl<A: Allocator + Copy> Problem<A> {
         tmp.extend(iter_ones_slice_usize(self.0.col_data(literal ^ 1)));
         tmp.iter().for_each(|&i| self.0.flip(i, literal ^ 1));
 
-        let mut cols = [literal ^ 1, literal];
-        cols.sort();
-        cols.into_iter()
-            .rev()
-            .for_each(|i| self.0.swap_remove_col(i));
+        let consistent = self.2.assign(literal);
+        self.3.assign(literal);
+        self.del_variable(literal);
+        if !consistent {
+            self.set_unsatisfiable();
+            return Ok(());
+        }
+        self.propagate_at_most(&mut tmp);
 
-        self.handle_shrinked(tmp);
+        self.handle_shrinked(tmp, budget)
     }
 
-    fn kernelize(&mut self) {
+    /// A literal of some unit clause.
+    fn unit(&self) -> Option<usize> {
+        (0..self.0.rows())
+            .map(|i| self.0.row_data(i))
+            .find(|x| x.iter().map(|x| x.count_ones()).sum::<u32>() == 1)
+            .and_then(|x| iter_ones_slice_usize(x).next())
+    }
+
+    fn kernelize(&mut self, budget: &mut Budget, trail: &mut Vec<isize>) {
         loop {
             let old_len = self.0.rows();
-            self.remove_pure_literals();
+            self.remove_pure_literals(budget, trail);
             if old_len == self.0.rows() {
                 break;
             }
         }
+        self.2.remove_trivial();
     }
 
     /// Establishes invariances.
-    pub(crate) fn prepare(&mut self) {
-        self.remove_tautologies();
-        let mut tmp = self.buffer();
-        tmp.extend(0..self.0.rows());
-        self.handle_shrinked(tmp);
+    pub(crate) fn prepare(&mut self, budget: &mut Budget) -> Result<(), Reason> {
+        self.remove_tautologies(budget);
+        self.subsume(budget)
     }
 
-    pub(crate) fn solve(mut self) -> bool {
-        if self.0.rows() == 1 {
-            if self.0.row_data(0).iter().all(|&x| x == 0) {
-                return false;
-            } else {
-                return true;
+    /// Returns `Err` as soon as the budget is exhausted. If satisfiable, `trail`
+    /// holds a (partial) model, variables missing from it are false.
+    pub(crate) fn solve(
+        mut self,
+        budget: &mut Budget,
+        trail: &mut Vec<isize>,
+        depth: usize,
+    ) -> Result<bool, Reason> {
+        budget.node(depth)?;
+
+        if self.0.rows() == 1 && self.2.is_empty() {
+            match iter_ones_slice_usize(self.0.row_data(0)).next() {
+                Some(x) => trail.push(self.literal(x)),
+                _ => {
+                    budget.conflict()?;
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        // Before unit propagation, which handles the units and empty clauses
+        // vivification can leave.
+        if budget.inprocessing && depth.is_multiple_of(INPROCESSING_INTERVAL) && depth > 0 {
+            self.vivify(budget)?;
+        }
+
+        // Unit propagation, which at-most constraints and the elimination of
+        // the XOR rows feed with their units.
+        loop {
+            while let Some(x) = self.unit() {
+                trail.push(self.literal(x));
+                self.resolve(x, budget)?;
             }
+            if !self.eliminate_xors(budget)? {
+                break;
+            }
+        }
+        if (0..self.0.rows()).any(|i| self.0.row_data(i).iter().all(|&x| x == 0)) {
+            budget.conflict()?;
+            return Ok(false);
         }
 
-        self.kernelize();
-        if self.0.rows() == 0 {
-            return true;
+        self.kernelize(budget, trail);
+        if self.0.rows() == 0 && self.2.is_empty() {
+            return Ok(true);
+        }
+        let len = trail.len();
+
+        if let Some(x) = self.solve_fragment(budget, trail) {
+            if !x {
+                budget.conflict()?;
+            }
+            return Ok(x);
+        }
+
+        // Components share no variable, so their models compose.
+        let (of, n) = self.components();
+        if n > 1 {
+            budget.stats.components += n as u64;
+            for c in 0..n {
+                if !self.component(&of, c).solve(budget, trail, depth)? {
+                    trail.truncate(len);
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
         }
 
         let mut cpy = self.clone();
@@ -335,23 +620,182 @@ impl<A: Allocator + Copy> Problem<A> {
         let choice = {
             match self.choose() {
                 Some(x) => x,
-                _ => return false,
+                _ => {
+                    budget.conflict()?;
+                    return Ok(false);
+                }
             }
         };
 
-        self.resolve(choice);
-        if self.solve() {
-            return true;
+        budget.decision()?;
+        trail.push(self.literal(choice));
+        self.resolve(choice, budget)?;
+        if self.solve(budget, trail, depth + 1)? {
+            return Ok(true);
         }
+        trail.truncate(len);
 
-        cpy.resolve(choice ^ 1);
-        if cpy.solve() {
-            return true;
+        trail.push(cpy.literal(choice ^ 1));
+        cpy.resolve(choice ^ 1, budget)?;
+        if cpy.solve(budget, trail, depth + 1)? {
+            return Ok(true);
         }
+        trail.truncate(len);
 
-        false
+        Ok(false)
     }
 }
 
 #[derive(Clone)]
-pub(crate) struct Problem<A: Allocator + Copy>(BitMatrix<A>);
+pub(crate) struct Problem<A: Allocator + Copy>(BitMatrix<A>, Vec<usize, A>, AtMost<A>, Xor<A>);
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::alloc::StacklikeAlloc;
+    use crate::limits::Limits;
+    use crate::preprocessing::Preprocessing;
+    use crate::solver::tests::{fired, nothing, random, samples, satisfies};
+    use crate::terminator::Terminator;
+
+    use core::alloc::Layout;
+    use std::alloc::Global;
+
+    pub(crate) fn problem(clauses: &[Vec<isize>]) -> Problem<Global> {
+        let mut x = Problem::new_in(Global);
+        clauses.iter().for_each(|c| x.add_clause(c.iter().copied()));
+        x
+    }
+
+    /// Run `f` with an unlimited budget.
+    pub(crate) fn unlimited<R>(f: impl FnOnce(&mut Budget) -> R) -> R {
+        let arena = StacklikeAlloc::new(Layout::from_size_align(4096, 4096).unwrap());
+        let terminator = Terminator::new();
+        f(&mut Budget::new(
+            Limits::default(),
+            &arena,
+            &terminator,
+            None,
+        ))
+    }
+
+    pub(crate) fn sorted(mut clauses: Vec<Vec<isize>>) -> Vec<Vec<isize>> {
+        clauses.iter_mut().for_each(|x| x.sort_unstable());
+        clauses.sort_unstable();
+        clauses
+    }
+
+    #[test]
+    fn variable_removal() {
+        let mut x = problem(&[vec![1, -2], vec![2, 3]]);
+        // The last variable takes the columns of the removed one.
+        x.del_variable(1);
+        assert_eq!((x.literal(0), x.literal(1), x.literal(2)), (3, -3, 2));
+        assert_eq!(x.column(-3), Some(1));
+        assert_eq!(sorted(x.to_vec()), [vec![-2], vec![2, 3]]);
+    }
+
+    #[test]
+    fn strengthening() {
+        // 1 ∨ 2 strengthens ¬1 ∨ 2 ∨ 3 and subsumes 1 ∨ 2 ∨ 4.
+        let mut x = problem(&[vec![1, 2], vec![-1, 2, 3], vec![1, 2, 4]]);
+        let stats = unlimited(|budget| {
+            let mut all = x.buffer();
+            all.extend(0..x.clauses());
+            x.handle_shrinked(all, budget).unwrap();
+            budget.stats
+        });
+        // Subsumed clauses are only marked, but none became a tautology.
+        assert_eq!(stats.subsumed, 1);
+        assert_eq!(x.to_vec(), [vec![1, 2], vec![2, 3], vec![1, 2]]);
+
+        // Also when no clause is left.
+        let mut x = problem(&[vec![1]]);
+        unlimited(|budget| x.resolve(0, budget)).unwrap();
+        assert_eq!(x.clauses(), 0);
+    }
+
+    #[test]
+    fn resolution() {
+        for seed in 0..200 {
+            let mut clauses = random(seed, 8, 30, 3);
+            clauses.retain(|x| x.iter().all(|l| !x.contains(&-l)));
+            let mut x = problem(&clauses);
+            unlimited(|budget| x.resolve(0, budget)).unwrap();
+            let rest = x.to_vec();
+            assert!(rest.iter().flatten().all(|l| l.unsigned_abs() != 1));
+
+            // Equivalent to the clauses with 1 true.
+            for bits in 0..1 << 7 {
+                let model: Vec<isize> = (1..=8)
+                    .map(|v| [-v, v][(v == 1 || bits >> (v - 2) & 1 == 1) as usize])
+                    .collect();
+                assert_eq!(satisfies(&model, &clauses), satisfies(&model, &rest));
+            }
+        }
+    }
+
+    #[test]
+    fn models() {
+        let mut sat = 0;
+        for seed in 0..200 {
+            let mut clauses = random(seed, 10, 40, 3);
+            clauses.retain(|x| x.iter().all(|l| !x.contains(&-l)));
+            let mut x = problem(&clauses);
+            let mut trail = Vec::new();
+            let found = unlimited(|budget| {
+                x.prepare(budget)?;
+                x.solve(budget, &mut trail, 0)
+            });
+            if !found.unwrap() {
+                continue;
+            }
+            // Variables missing from the trail are false.
+            let mut model: Vec<isize> = (1..=10).map(|v| -v).collect();
+            trail.iter().for_each(|&l| model[l.unsigned_abs() - 1] = l);
+            assert!(satisfies(&model, &clauses));
+            sat += 1;
+        }
+        assert!(sat > 0);
+    }
+
+    #[test]
+    fn blocked_clause() {
+        // Only 5 ∨ ¬4 is blocked, the others have a resolvent each.
+        let rest = [
+            vec![-4, -1],
+            vec![-3, -2, -1],
+            vec![-3, 2, 4],
+            vec![-2, 1],
+            vec![-2, 3, 4],
+            vec![-1, 3],
+            vec![1, 2, 3],
+        ];
+        let mut clauses = rest.to_vec();
+        clauses.push(vec![5, -4]);
+        let mut x = problem(&clauses);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            x.remove_blocked_clauses(budget, &mut rec).unwrap();
+            budget.stats
+        });
+        assert_eq!(stats.blocked, 1);
+        assert_eq!(sorted(x.to_vec()), rest);
+        assert_eq!(rec.to_string(), "5 -4 5 0\n");
+
+        // A model of the rest that violates it.
+        let mut model = vec![-1, -2, 3, 4, -5];
+        assert!(satisfies(&model, &rest));
+        rec.extend(&mut model);
+        assert_eq!(model, [-1, -2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn blocked_clauses() {
+        let blocked = Preprocessing {
+            blocked: true,
+            ..nothing()
+        };
+        assert_eq!(fired(blocked, &samples(), |x| x.blocked), (true, true));
+    }
+}
@@ -0,0 +1,157 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+use crate::reconstruction::Reconstruction;
+
+use core::alloc::Allocator;
+
+/// Literals whose implications are tried as autarkies, per round.
+const AUTARKY_PROBES: usize = 1 << 10;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Shrink the assignment `value` to the largest autarky within it, s.t.
+    /// every clause over one of its variables is satisfied. Clauses without a
+    /// true literal unassign their variables until there are none.
+    fn trim_autarky(&self, value: &mut [Option<usize>]) -> bool {
+        let mut active = vec![0; self.0.rows()];
+        let mut sat = vec![0; self.0.rows()];
+        for &t in value.iter().flatten() {
+            iter_ones_slice_usize(self.0.col_data(t)).for_each(|c| sat[c] += 1);
+            for c in iter_ones_slice_usize(self.0.col_data(t))
+                .chain(iter_ones_slice_usize(self.0.col_data(t ^ 1)))
+            {
+                active[c] += 1;
+            }
+        }
+
+        let mut queue: Vec<usize> = (0..self.0.rows())
+            .filter(|&c| active[c] > 0 && sat[c] == 0)
+            .collect();
+        while let Some(c) = queue.pop() {
+            for l in iter_ones_slice_usize(self.0.row_data(c)) {
+                let Some(t) = value[l >> 1].take() else {
+                    continue;
+                };
+                for d in iter_ones_slice_usize(self.0.col_data(t)) {
+                    active[d] -= 1;
+                    sat[d] -= 1;
+                    if active[d] > 0 && sat[d] == 0 {
+                        queue.push(d);
+                    }
+                }
+                iter_ones_slice_usize(self.0.col_data(t ^ 1)).for_each(|d| active[d] -= 1);
+            }
+        }
+        value.iter().any(Option::is_some)
+    }
+
+    /// A non-empty autarky trimmed from one of the candidates: the literals
+    /// occurring more often, their negations and the implications of single
+    /// literals. Variables of at-most constraints are left out.
+    fn find_autarky(&self, budget: &mut Budget) -> Result<Option<Vec<Option<usize>>>, Reason> {
+        let count = |l| {
+            self.0
+                .col_data(l)
+                .iter()
+                .map(|x| x.count_ones())
+                .sum::<u32>()
+        };
+        let free = |v: usize| !self.2.contains(v << 1) && !self.2.contains((v << 1) | 1);
+        let majority: Vec<Option<usize>> = (0..self.variables())
+            .map(|v| free(v).then(|| (v << 1) | (count(v << 1) < count((v << 1) | 1)) as usize))
+            .collect();
+
+        for flip in [0, 1] {
+            let mut value: Vec<_> = majority.iter().map(|x| x.map(|l| l ^ flip)).collect();
+            if self.trim_autarky(&mut value) {
+                return Ok(Some(value));
+            }
+        }
+
+        let mut prop = self.propagator();
+        if !prop.propagate() {
+            return Ok(None);
+        }
+        let root = prop.trail.len();
+        for l in (0..self.literals()).take(AUTARKY_PROBES) {
+            budget.check()?;
+            if !free(l >> 1) || prop.value(l).is_some() {
+                continue;
+            }
+            prop.assume(l);
+            if prop.propagate() {
+                let mut value = vec![None; self.variables()];
+                for &t in &prop.trail {
+                    value[t >> 1] = free(t >> 1).then_some(t);
+                }
+                if self.trim_autarky(&mut value) {
+                    return Ok(Some(value));
+                }
+            }
+            prop.backtrack(root);
+        }
+        Ok(None)
+    }
+
+    /// Remove the clauses satisfied by autarkies, which generalize pure
+    /// literals. Their literals are set unconditionally by `rec`.
+    pub(crate) fn remove_autarkies(
+        &mut self,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> Result<(), Reason> {
+        while let Some(value) = self.find_autarky(budget)? {
+            let mut to_delete = self.buffer();
+            for &t in value.iter().flatten() {
+                rec.push(self.literal(t), vec![self.literal(t)]);
+                to_delete.extend(iter_ones_slice_usize(self.0.col_data(t)));
+            }
+            to_delete.sort_unstable();
+            to_delete.dedup();
+            budget.stats.autark += to_delete.len() as u64;
+            to_delete.iter().rev().for_each(|&i| self.del_clause(i));
+
+            for v in (0..value.len()).rev() {
+                if value[v].is_some() {
+                    self.del_variable(v << 1);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, sorted, unlimited};
+    use crate::solver::tests::{fired, nothing, samples};
+
+    #[test]
+    fn autarkies() {
+        let autarky = Preprocessing {
+            autarky: true,
+            ..nothing()
+        };
+        let x = fired(autarky, &samples(), |x| x.autark);
+        assert_eq!(x, (true, true));
+    }
+
+    #[test]
+    fn lean_rest() {
+        // 3 and 4 true satisfy the clauses they touch, the rest has no autarky.
+        let core = [vec![-2, -1], vec![-2, 1], vec![-1, 2], vec![1, 2]];
+        let mut clauses = core.to_vec();
+        clauses.extend([vec![3, 1], vec![-3, 4], vec![4, -2]]);
+        let mut x = problem(&clauses);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            x.remove_autarkies(budget, &mut rec).unwrap();
+            budget.stats
+        });
+        assert_eq!(stats.autark, 3);
+        assert_eq!(sorted(x.to_vec()), core);
+        assert_eq!(rec.to_string(), "3 3 0\n4 4 0\n");
+    }
+}
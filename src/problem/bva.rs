@@ -0,0 +1,174 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+use core::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Literals in more clauses are not matched.
+const ADDITION_BOUND: usize = 1 << 10;
+
+/// Clauses saved by replacing `lits` × `cls` clauses by one per element.
+const fn reduction(lits: usize, cls: usize) -> isize {
+    (lits * cls) as isize - (lits + cls) as isize
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Bounded variable addition: replace clauses (m ∨ R) for all m in a set
+    /// of literals and all R in a set of clause rests by (m ∨ x) and (R ∨ ¬x)
+    /// for a fresh variable x numbered after `vars`, as long as this saves
+    /// clauses.
+    pub(crate) fn add_variables(
+        &mut self,
+        budget: &mut Budget,
+        vars: &mut usize,
+    ) -> Result<(), Reason> {
+        let mut l = 0;
+        while l < self.literals() {
+            budget.check()?;
+            match self.addition_match(l) {
+                Some((lits, cls)) => {
+                    self.replace_match(l, &lits, &cls, vars);
+                    budget.stats.added += 1;
+                }
+                None => l += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// The literals of `clause` but `literal`, and the clauses which consist
+    /// of them and one other literal, with that literal.
+    fn partners(&self, clause: usize, literal: usize) -> (Vec<usize>, Vec<(usize, usize)>) {
+        let rest: Vec<usize> = iter_ones_slice_usize(self.0.row_data(clause))
+            .filter(|&m| m != literal)
+            .collect();
+        let Some((&first, others)) = rest.split_first() else {
+            return (rest, Vec::new());
+        };
+
+        let mut common = self.0.col_data(first).to_vec();
+        for &m in others {
+            common
+                .iter_mut()
+                .zip(self.0.col_data(m))
+                .for_each(|(x, &y)| *x &= y);
+        }
+        let res = iter_ones_slice_usize(&common)
+            .filter_map(|d| {
+                let mut x = iter_ones_slice_usize(self.0.row_data(d)).filter(|m| !rest.contains(m));
+                match (x.next(), x.next()) {
+                    (Some(m), None) => Some((d, m)),
+                    _ => None,
+                }
+            })
+            .collect();
+        (rest, res)
+    }
+
+    /// Grow the matched literals from `literal` on, each time by the one with
+    /// most partners of the matched clauses, while the reduction increases.
+    fn addition_match(&self, literal: usize) -> Option<(Vec<usize>, Vec<usize>)> {
+        let mut lits = vec![literal];
+        let mut cls: Vec<usize> = iter_ones_slice_usize(self.0.col_data(literal)).collect();
+        if cls.len() > ADDITION_BOUND {
+            return None;
+        }
+
+        loop {
+            let mut found: HashMap<usize, Vec<usize>> = HashMap::new();
+            for &c in &cls {
+                for (_, m) in self.partners(c, literal).1 {
+                    if !lits.contains(&m) {
+                        found.entry(m).or_default().push(c);
+                    }
+                }
+            }
+            let best = found
+                .into_iter()
+                .max_by_key(|(m, x)| (x.len(), Reverse(*m)));
+            match best {
+                Some((m, x))
+                    if reduction(lits.len() + 1, x.len()) > reduction(lits.len(), cls.len()) =>
+                {
+                    lits.push(m);
+                    cls = x;
+                }
+                _ => break,
+            }
+        }
+        (reduction(lits.len(), cls.len()) > 0).then_some((lits, cls))
+    }
+
+    fn replace_match(&mut self, literal: usize, lits: &[usize], cls: &[usize], vars: &mut usize) {
+        let mut to_delete = Vec::new();
+        let mut rests = Vec::new();
+        for &c in cls {
+            let (rest, partners) = self.partners(c, literal);
+            to_delete.extend(
+                partners
+                    .into_iter()
+                    .filter(|(_, m)| lits.contains(m))
+                    .map(|x| x.0),
+            );
+            rests.push(rest);
+        }
+        to_delete.sort_unstable();
+        to_delete.dedup();
+        to_delete.iter().rev().for_each(|&i| self.del_clause(i));
+
+        let x = self.fresh_variable(vars);
+        for &m in lits {
+            self.add_row([m, x].into_iter());
+        }
+        for rest in rests {
+            self.add_row(rest.into_iter().chain([x ^ 1]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, sorted, unlimited};
+    use crate::solver::tests::{fired, nothing};
+
+    #[test]
+    fn addition() {
+        let addition = Preprocessing {
+            addition: true,
+            ..nothing()
+        };
+        // a ≠ b for a in {1, 2, 3} and b in {4, 5, 6}, as 18 clauses.
+        let mut sat = Vec::new();
+        for a in 1..=3 {
+            for b in 4..=6 {
+                sat.extend([vec![a, b], vec![-a, -b]]);
+            }
+        }
+        // 1 and 2 are false, 4 and 5 true.
+        let mut unsat = sat.clone();
+        unsat.extend([vec![-1, -2], vec![-4, -5]]);
+        let x = fired(addition, &[(6, sat), (6, unsat)], |x| x.added);
+        assert_eq!(x, (true, true));
+    }
+
+    #[test]
+    fn product() {
+        // {1, 2, 3} × {4, 5, 6} becomes (m ∨ 7) and (¬7 ∨ r).
+        let mut clauses = Vec::new();
+        for m in 1..=3 {
+            clauses.extend((4..=6).map(|r| vec![m, r]));
+        }
+        let mut x = problem(&clauses);
+        let mut vars = 6;
+        let stats = unlimited(|budget| {
+            x.add_variables(budget, &mut vars).unwrap();
+            budget.stats
+        });
+        assert_eq!((stats.added, vars), (1, 7));
+        let expected = [[-7, 4], [-7, 5], [-7, 6], [1, 7], [2, 7], [3, 7]];
+        assert_eq!(sorted(x.to_vec()), expected.map(|x| x.to_vec()));
+    }
+}
@@ -0,0 +1,370 @@
+use super::Problem;
+use crate::bits::bit_matrix::BitMatrix;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+use std::collections::{HashMap, HashSet};
+
+/// Clauses longer than this do not take part in at-most-k groups.
+const AT_MOST_BOUND: usize = 3;
+
+/// All sorted subsets of `items` with `k` elements.
+fn subsets(items: &[usize], k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    let mut res = Vec::new();
+    for i in 0..items.len() {
+        for mut x in subsets(&items[i + 1..], k - 1) {
+            x.insert(0, items[i]);
+            res.push(x);
+        }
+    }
+    res
+}
+
+impl<A: Allocator + Copy> AtMost<A> {
+    pub(super) fn new_in(a: A) -> Self {
+        Self(BitMatrix::new_in(a), Vec::new_in(a))
+    }
+
+    pub(super) fn push_empty_col(&mut self) {
+        self.0.push_empty_col();
+    }
+
+    pub(super) fn swap_remove_col(&mut self, col: usize) {
+        self.0.swap_remove_col(col);
+    }
+
+    pub(super) const fn is_empty(&self) -> bool {
+        self.0.rows() == 0
+    }
+
+    pub(super) const fn len(&self) -> usize {
+        self.0.rows()
+    }
+
+    /// The number of constraints over `literal`.
+    pub(super) fn count(&self, literal: usize) -> u32 {
+        self.0
+            .col_data(literal)
+            .iter()
+            .map(|x| x.count_ones())
+            .sum()
+    }
+
+    /// Whether `literal` occurs in a constraint.
+    pub(super) fn contains(&self, literal: usize) -> bool {
+        self.0.col_data(literal).iter().any(|&x| x != 0)
+    }
+
+    fn push<I: Iterator<Item = usize>>(&mut self, k: usize, literals: I) {
+        let row = self.0.rows();
+        self.0.push_empty_row();
+        literals.for_each(|l| self.0.set(row, l));
+        self.1.push(k);
+    }
+
+    fn del(&mut self, constraint: usize) {
+        self.0.swap_remove_row(constraint);
+        self.1.swap_remove(constraint);
+    }
+
+    pub(super) fn clear(&mut self) {
+        while self.0.rows() > 0 {
+            self.del(self.0.rows() - 1);
+        }
+    }
+
+    /// The constraints over `literal` as a bitset.
+    pub(super) fn occurrences(&self, literal: usize) -> &[usize] {
+        self.0.col_data(literal)
+    }
+
+    /// The literals of `constraint` as a bitset.
+    pub(super) fn literals(&self, constraint: usize) -> &[usize] {
+        self.0.row_data(constraint)
+    }
+
+    /// Keep the constraints whose literals satisfy `f`.
+    pub(super) fn retain<F: Fn(&[usize]) -> bool>(&mut self, f: F) {
+        for i in (0..self.0.rows()).rev() {
+            if !f(self.0.row_data(i)) {
+                self.del(i);
+            }
+        }
+    }
+
+    /// The bound and literals of each constraint.
+    pub(super) fn constraints(&self) -> impl Iterator<Item = (usize, &[usize])> {
+        (0..self.0.rows()).map(|i| (self.1[i], self.0.row_data(i)))
+    }
+
+    /// Delete the constraints which no assignment can violate anymore.
+    pub(super) fn remove_trivial(&mut self) {
+        for i in (0..self.0.rows()).rev() {
+            let len: u32 = self.0.row_data(i).iter().map(|x| x.count_ones()).sum();
+            if self.1[i] >= len as usize {
+                self.del(i);
+            }
+        }
+    }
+
+    /// Count `literal` as true, returns `false` if a bound is exceeded.
+    pub(super) fn assign(&mut self, literal: usize) -> bool {
+        for i in iter_ones_slice_usize(self.0.col_data(literal)) {
+            if self.1[i] == 0 {
+                return false;
+            }
+            self.1[i] -= 1;
+        }
+        true
+    }
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Add the constraint that at most `k` of the DIMACS `literals` are true,
+    /// taken as a set. Only valid as long as no variable has been removed.
+    pub(crate) fn add_at_most(&mut self, k: usize, literals: &[isize]) {
+        let mut cols: Vec<usize> = literals
+            .iter()
+            .map(|&l| {
+                self.reserve(l.unsigned_abs());
+                ((l.unsigned_abs() - 1) << 1) | l.is_negative() as usize
+            })
+            .collect();
+        cols.sort_unstable();
+        cols.dedup();
+
+        // A literal and its negation count exactly once together.
+        let mut k = k as isize;
+        let mut i = 0;
+        while i + 1 < cols.len() {
+            if cols[i] ^ 1 == cols[i + 1] {
+                cols.drain(i..i + 2);
+                k -= 1;
+            } else {
+                i += 1;
+            }
+        }
+
+        match k {
+            ..0 => self.add_row(core::iter::empty()),
+            0 => cols.iter().for_each(|&l| self.add_row([l ^ 1].into_iter())),
+            k if k as usize >= cols.len() => (),
+            k => self.2.push(k as usize, cols.into_iter()),
+        }
+    }
+
+    /// Literals left alone in constraints whose bound is used up are false,
+    /// their unit clauses are pushed onto `shrinked`. Trivial ones are deleted.
+    pub(super) fn propagate_at_most<B: Allocator>(&mut self, shrinked: &mut Vec<usize, B>) {
+        for i in (0..self.2.0.rows()).rev() {
+            if self.2.1[i] == 0 {
+                let mut tmp = self.buffer();
+                tmp.extend(iter_ones_slice_usize(self.2.0.row_data(i)));
+                for l in tmp {
+                    shrinked.push(self.0.rows());
+                    self.add_row([l ^ 1].into_iter());
+                }
+                self.2.del(i);
+            }
+        }
+        self.2.remove_trivial();
+    }
+
+    /// The clauses equivalent to the constraints, in DIMACS literals.
+    pub(super) fn at_most_clauses(&self) -> Vec<Vec<isize>> {
+        let mut res = Vec::new();
+        for i in 0..self.2.0.rows() {
+            let cols: Vec<usize> = iter_ones_slice_usize(self.2.0.row_data(i)).collect();
+            for x in subsets(&cols, self.2.1[i] + 1) {
+                res.push(x.iter().map(|&l| self.literal(l ^ 1)).collect());
+            }
+        }
+        res
+    }
+
+    /// Replace groups of clauses spelling out an at-most-k constraint over at
+    /// least k + 2 literals, i.e. all (k + 1)-subsets of negated literals, by
+    /// the native constraint.
+    pub(crate) fn detect_at_most(&mut self, budget: &mut Budget) -> Result<(), Reason> {
+        let mut to_delete = Vec::new();
+        let mut found = Vec::new();
+        for size in 2..=AT_MOST_BOUND {
+            let k = size - 1;
+
+            // Negated literals of each clause ↦ its row.
+            let mut edges = HashMap::new();
+            let mut order = Vec::new();
+            // Literals completing k of them to an edge.
+            let mut completions = HashMap::<Vec<usize>, Vec<usize>>::new();
+            for i in 0..self.0.rows() {
+                let e: Vec<usize> = iter_ones_slice_usize(self.0.row_data(i))
+                    .map(|l| l ^ 1)
+                    .collect();
+                if e.len() != size || e.windows(2).any(|x| x[0] >> 1 == x[1] >> 1) {
+                    continue;
+                }
+                let mut e = e;
+                e.sort_unstable();
+                for j in 0..size {
+                    let mut sub = e.clone();
+                    let d = sub.remove(j);
+                    completions.entry(sub).or_default().push(d);
+                }
+                order.push(e.clone());
+                edges.insert(e, i);
+            }
+
+            let mut used = HashSet::new();
+            for e in order {
+                if used.contains(&e) {
+                    continue;
+                }
+                budget.check()?;
+
+                let mut group = e.clone();
+                for &d in &completions[&e[..k]] {
+                    if group.contains(&d) {
+                        continue;
+                    }
+                    if subsets(&group, k).into_iter().all(|mut x| {
+                        x.push(d);
+                        x.sort_unstable();
+                        edges.contains_key(&x)
+                    }) {
+                        group.push(d);
+                    }
+                }
+                if group.len() < size + 1 {
+                    continue;
+                }
+
+                group.sort_unstable();
+                for x in subsets(&group, size) {
+                    to_delete.push(edges[&x]);
+                    used.insert(x);
+                }
+                budget.stats.at_most += 1;
+                if k == 1 && self.contains_clause(&group) {
+                    budget.stats.exactly_one += 1;
+                }
+                found.push((k, group));
+            }
+        }
+
+        to_delete.sort_unstable();
+        to_delete.dedup();
+        to_delete.iter().rev().for_each(|&i| self.del_clause(i));
+        for (k, group) in found {
+            self.2.push(k, group.into_iter());
+        }
+        Ok(())
+    }
+
+    /// Whether the clause of exactly the sorted columns `literals` exists.
+    fn contains_clause(&self, literals: &[usize]) -> bool {
+        self.find_clause(literals).is_some()
+    }
+}
+
+/// At-most-k constraints, rows are constraints over the literal columns of
+/// the problem and the bounds k are kept alongside.
+#[derive(Clone)]
+pub(super) struct AtMost<A: Allocator + Copy>(BitMatrix<A>, Vec<usize, A>);
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocessing::Preprocessing;
+    use crate::solver::tests::{check, nothing, random, satisfies, solver};
+    use crate::solver::{Solution, SolverError};
+
+    /// Whether at most `k` of the distinct `literals` are true in `model`.
+    fn at_most(model: &[isize], k: usize, literals: &[isize]) -> bool {
+        let mut x = literals.to_vec();
+        x.sort_unstable();
+        x.dedup();
+        x.iter().filter(|l| model.contains(l)).count() <= k
+    }
+
+    #[test]
+    fn detection() {
+        let cardinality = Preprocessing {
+            cardinality: true,
+            ..nothing()
+        };
+        let mut clauses = vec![vec![1, 2, 3, 4]];
+        for i in 1..=4 {
+            clauses.extend((i + 1..=4).map(|j| vec![-i, -j]));
+        }
+        let (sat, stats) = check(cardinality, 4, &clauses);
+        assert!(sat && stats.at_most == 1 && stats.exactly_one == 1);
+        clauses.extend([vec![1, 2], vec![3, 4]]);
+        assert!(!check(cardinality, 4, &clauses).0);
+
+        // At most 2 of 5, but 3 are needed.
+        let mut clauses = vec![vec![1, 2], vec![3, 4], vec![5]];
+        for i in 1..=5 {
+            for j in i + 1..=5 {
+                clauses.extend((j + 1..=5).map(|k| vec![-i, -j, -k]));
+            }
+        }
+        let (sat, stats) = check(cardinality, 5, &clauses);
+        assert!(!sat && stats.at_most == 1);
+        clauses.remove(2);
+        assert!(check(cardinality, 5, &clauses).0);
+    }
+
+    #[test]
+    fn propagation() {
+        let mut x = solver(3, &[vec![1]]);
+        x.add_at_most(1, vec![1, 2, 3]).unwrap();
+        let Ok(Solution::Satisfiable(model)) = x.solve() else {
+            panic!()
+        };
+        assert_eq!(model, [1, -2, -3]);
+
+        // A literal and its negation use up the bound.
+        let mut x = solver(2, &[vec![2]]);
+        x.add_at_most(1, vec![1, -1, 2]).unwrap();
+        assert!(matches!(x.solve(), Ok(Solution::Unsatisfiable)));
+
+        for seed in 0..100 {
+            let clauses = random(seed, 8, 14, 3);
+            let constraints = random(seed + 100, 8, 3, 4);
+            let valid = |model: &[isize]| {
+                let mut k = 0..;
+                satisfies(model, &clauses)
+                    && constraints
+                        .iter()
+                        .all(|x| at_most(model, k.next().unwrap() % 3, x))
+            };
+            let expected = (0..1 << 8).any(|bits| {
+                let model: Vec<isize> = (1..=8).map(|v| [-v, v][(bits >> (v - 1)) & 1]).collect();
+                valid(&model)
+            });
+            for preprocessing in [nothing(), Preprocessing::default()] {
+                let mut x = solver(8, &clauses);
+                x.set_preprocessing(preprocessing);
+                for (k, literals) in constraints.iter().enumerate() {
+                    x.add_at_most(k % 3, literals.clone()).unwrap();
+                }
+                match x.solve().unwrap() {
+                    Solution::Satisfiable(model) => assert!(expected && valid(&model)),
+                    x => assert!(!expected && matches!(x, Solution::Unsatisfiable)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn after_preprocess() {
+        let mut solver = solver(4, &random(0, 4, 6, 2));
+        solver.add_at_most(2, vec![1, 2, 3]).unwrap();
+        solver.preprocess().unwrap();
+        let x = solver.add_at_most(1, vec![1, 4]);
+        assert!(matches!(x, Err(SolverError::Simplified)));
+    }
+}
@@ -0,0 +1,107 @@
+use super::Problem;
+use crate::bits::bit_tools::{BITS, integers_needed, iter_ones_slice_usize};
+
+use core::alloc::Allocator;
+
+/// Mark the rows of `col` in `reached` and push those not marked before.
+fn reach(reached: &mut [usize], col: &[usize], new: &mut Vec<usize>) {
+    for (i, (x, &y)) in reached.iter_mut().zip(col).enumerate() {
+        new.extend(iter_ones_slice_usize(&[y & !*x]).map(|j| i * BITS + j));
+        *x |= y;
+    }
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// The component of each variable and their number, where clauses and
+    /// at-most constraints connect their variables. Rows are reached through
+    /// the columns of the variables found so far.
+    pub(super) fn components(&self) -> (Vec<usize>, usize) {
+        let mut of = vec![usize::MAX; self.variables()];
+        let mut clauses = vec![0; self.0.integers_used_each_col()];
+        let mut at_most = vec![0; integers_needed(self.2.len())];
+        let (mut stack, mut rows, mut constraints) = (Vec::new(), Vec::new(), Vec::new());
+        let mut n = 0;
+
+        for v in 0..self.variables() {
+            if of[v] != usize::MAX {
+                continue;
+            }
+            of[v] = n;
+            stack.push(v);
+            while let Some(v) = stack.pop() {
+                for l in [v << 1, (v << 1) | 1] {
+                    reach(&mut clauses, self.0.col_data(l), &mut rows);
+                    reach(&mut at_most, self.2.occurrences(l), &mut constraints);
+                }
+                let lits = rows
+                    .drain(..)
+                    .flat_map(|i| iter_ones_slice_usize(self.0.row_data(i)));
+                let more = constraints
+                    .drain(..)
+                    .flat_map(|i| iter_ones_slice_usize(self.2.literals(i)));
+                for w in lits.chain(more).map(|l| l >> 1) {
+                    if of[w] == usize::MAX {
+                        of[w] = n;
+                        stack.push(w);
+                    }
+                }
+            }
+            n += 1;
+        }
+        (of, n)
+    }
+
+    /// The clauses, at-most constraints and XOR rows over the variables of
+    /// component `c`, where `of` is the first result of `components`.
+    pub(super) fn component(&self, of: &[usize], c: usize) -> Self {
+        let first = |row: &[usize]| iter_ones_slice_usize(row).next().map(|l| of[l >> 1]);
+        let mut res = self.clone();
+        for i in (0..res.0.rows()).rev() {
+            if first(res.0.row_data(i)) != Some(c) {
+                res.del_clause(i);
+            }
+        }
+        res.2.retain(|x| first(x) == Some(c));
+        // XOR rows only help, those across components are dropped.
+        res.3
+            .retain(|x| iter_ones_slice_usize(x).all(|l| of[l >> 1] == c));
+        for v in (0..of.len()).rev() {
+            if of[v] != c {
+                res.del_variable(v << 1);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::problem::tests::problem;
+    use crate::solver::tests::{fired, nothing, samples};
+
+    #[test]
+    fn independent() {
+        // Each sample next to the following one, over disjoint variables.
+        let formulas = samples();
+        let pairs: Vec<_> = formulas
+            .iter()
+            .zip(formulas.iter().skip(1))
+            .map(|((n, x), (m, y))| {
+                let shift = |l: &isize| l + l.signum() * *n as isize;
+                let mut clauses = x.clone();
+                clauses.extend(y.iter().map(|c| c.iter().map(shift).collect()));
+                (n + m, clauses)
+            })
+            .collect();
+        assert_eq!(fired(nothing(), &pairs, |x| x.components), (true, true));
+    }
+
+    #[test]
+    fn partition() {
+        // {1, 2, 5} and {3, 4, 6}, numbered by their first variable.
+        let x = problem(&[vec![1, 2], vec![3, 4], vec![2, -5], vec![-4, 6]]);
+        assert_eq!(x.components(), (vec![0, 0, 1, 1, 0, 1], 2));
+        let first = x.component(&x.components().0, 0);
+        assert_eq!(first.to_vec(), [vec![1, 2], vec![2, -5]]);
+    }
+}
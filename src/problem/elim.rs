@@ -0,0 +1,201 @@
+use super::{Problem, zip_with};
+use crate::bits::bit_tools::{BITS, iter_ones_slice_usize};
+use crate::limits::{Budget, Reason};
+use crate::reconstruction::Reconstruction;
+
+use core::alloc::Allocator;
+use core::ops::BitOr;
+
+/// Bits of the positive literals, their negations are one bit higher.
+const EVEN: usize = usize::MAX / 3;
+
+/// Variables with more candidate resolvents are not tried.
+const RESOLVENT_BOUND: usize = 1 << 10;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Replace the clauses of a variable by their resolvents, as long as this
+    /// does not increase the number of clauses (bounded variable elimination).
+    pub(crate) fn eliminate_variables(
+        &mut self,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> Result<(), Reason> {
+        loop {
+            let mut changed = false;
+            let mut i = 0;
+            while i < self.literals() {
+                budget.check()?;
+                if self.eliminate_variable(i, budget, rec) {
+                    budget.stats.eliminated += 1;
+                    changed = true;
+                } else {
+                    i += 2;
+                }
+            }
+
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Try to eliminate the variable of the positive column `literal`.
+    fn eliminate_variable(
+        &mut self,
+        literal: usize,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> bool {
+        if self.2.contains(literal) || self.2.contains(literal ^ 1) {
+            return false;
+        }
+        let mut pos = self.buffer();
+        let mut neg = self.buffer();
+        pos.extend(iter_ones_slice_usize(self.0.col_data(literal)));
+        neg.extend(iter_ones_slice_usize(self.0.col_data(literal ^ 1)));
+        if pos.len() * neg.len() > RESOLVENT_BOUND {
+            return false;
+        }
+
+        // If the variable is defined by a gate, only clauses of the gate are
+        // resolved with the others. Resolvents within either group are
+        // implied by those (gate-defined elimination).
+        let gate = self.gate_of(literal).map(|x| x.1).unwrap_or_default();
+
+        let words = self.0.integers_used_each_row();
+        let (i, j) = (literal / BITS, literal % BITS);
+        let mut resolvents = self.buffer();
+        for &p in &pos {
+            for &n in &neg {
+                if !gate.is_empty() && gate.contains(&p) == gate.contains(&n) {
+                    continue;
+                }
+                let start = resolvents.len();
+                resolvents.extend(zip_with(
+                    self.0.row_data(p),
+                    self.0.row_data(n),
+                    BitOr::bitor,
+                ));
+                resolvents[start + i] &= !(3 << j);
+
+                if resolvents[start..]
+                    .iter()
+                    .any(|&x| x & (x >> 1) & EVEN != 0)
+                {
+                    resolvents.truncate(start);
+                } else if resolvents.len() > (pos.len() + neg.len()) * words {
+                    return false;
+                }
+            }
+        }
+
+        budget.stats.gate_eliminated += !gate.is_empty() as u64;
+        for &k in &pos {
+            rec.push(self.literal(literal), self.clause(k));
+        }
+        for &k in &neg {
+            rec.push(self.literal(literal ^ 1), self.clause(k));
+        }
+
+        pos.extend_from_slice(&neg);
+        pos.sort_unstable();
+        pos.dedup();
+        pos.iter().rev().for_each(|&k| self.del_clause(k));
+
+        // The resolvents still use the old columns, so they go in first.
+        if words > 0 {
+            for x in resolvents.chunks(words) {
+                self.add_row(iter_ones_slice_usize(x));
+            }
+        }
+        self.del_variable(literal);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, sorted, unlimited};
+    use crate::solver::tests::{check, fired, nothing, random, samples};
+
+    #[test]
+    fn gate_resolvents() {
+        // 1 = 2 ∧ 3, with 1 ∨ 4 and ¬1 ∨ 5 besides.
+        let clauses = [
+            vec![-1, 2],
+            vec![-1, 3],
+            vec![1, -2, -3],
+            vec![1, 4],
+            vec![-1, 5],
+        ];
+        let mut x = problem(&clauses);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            assert!(x.eliminate_variable(0, budget, &mut rec));
+            budget.stats
+        });
+        assert_eq!(stats.gate_eliminated, 1);
+
+        // Without 4 ∨ 5 of the clauses outside the gate.
+        let expected = vec![vec![-3, -2, 5], vec![2, 4], vec![3, 4]];
+        assert_eq!(sorted(x.to_vec()), expected);
+    }
+
+    #[test]
+    fn resolvents() {
+        // No gate defines 1, all its clauses are resolved.
+        let clauses = [vec![1, 2], vec![1, 3], vec![-1, 4], vec![-2, -3]];
+        let mut x = problem(&clauses);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            assert!(x.eliminate_variable(0, budget, &mut rec));
+            budget.stats
+        });
+        assert_eq!(stats.gate_eliminated, 0);
+        let expected = vec![vec![-3, -2], vec![2, 4], vec![3, 4]];
+        assert_eq!(sorted(x.to_vec()), expected);
+        assert_eq!(rec.to_string(), "1 1 2 0\n1 1 3 0\n-1 -1 4 0\n");
+    }
+
+    #[test]
+    fn gate_elimination() {
+        let elimination = Preprocessing {
+            elimination: true,
+            ..nothing()
+        };
+        let (mut sat, mut unsat, mut gates) = (0, 0, 0);
+        for seed in 0..100 {
+            // 1 = 2 ∧ 3 or 1 = 2 ⊕ 3 in random clauses.
+            let mut clauses = random(seed, 8, 18, 3);
+            clauses.extend(match seed % 2 {
+                0 => [vec![-1, 2], vec![-1, 3], vec![1, -2, -3]].to_vec(),
+                _ => [
+                    vec![-1, 2, 3],
+                    vec![-1, -2, -3],
+                    vec![1, -2, 3],
+                    vec![1, 2, -3],
+                ]
+                .to_vec(),
+            });
+            let (x, stats) = check(elimination, 8, &clauses);
+            match x {
+                true => sat += 1,
+                false => unsat += 1,
+            }
+            gates += stats.gate_eliminated;
+        }
+        assert!(sat > 0 && unsat > 0 && gates > 0);
+    }
+
+    #[test]
+    fn elimination() {
+        let elimination = Preprocessing {
+            elimination: true,
+            ..nothing()
+        };
+        let x = fired(elimination, &samples(), |x| x.eliminated);
+        assert_eq!(x, (true, true));
+    }
+}
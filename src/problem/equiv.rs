@@ -0,0 +1,177 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+use crate::reconstruction::Reconstruction;
+
+use core::alloc::Allocator;
+
+/// Tarjan's algorithm without recursion, returns the component of each node.
+/// Components are numbered in reverse topological order.
+pub(super) fn strongly_connected(adj: &[Vec<usize>]) -> Vec<usize> {
+    let n = adj.len();
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut comp = vec![usize::MAX; n];
+    let mut on_stack = vec![false; n];
+    let (mut stack, mut call) = (Vec::new(), Vec::new());
+    let (mut counter, mut comps) = (0, 0);
+
+    for s in 0..n {
+        if index[s] != usize::MAX {
+            continue;
+        }
+        index[s] = counter;
+        low[s] = counter;
+        counter += 1;
+        stack.push(s);
+        on_stack[s] = true;
+        call.push((s, 0));
+
+        while let Some(&(v, i)) = call.last() {
+            if let Some(&w) = adj[v].get(i) {
+                call.last_mut().unwrap().1 += 1;
+                if index[w] == usize::MAX {
+                    index[w] = counter;
+                    low[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+
+            call.pop();
+            if let Some(&(u, _)) = call.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    comp[w] = comps;
+                    if w == v {
+                        break;
+                    }
+                }
+                comps += 1;
+            }
+        }
+    }
+    comp
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// The binary implication graph, ¬a → b and ¬b → a for each clause (a ∨ b).
+    pub(super) fn implication_graph(&self) -> Vec<Vec<usize>> {
+        let mut adj = vec![Vec::new(); self.literals()];
+        for i in 0..self.0.rows() {
+            let mut it = iter_ones_slice_usize(self.0.row_data(i));
+            if let (Some(a), Some(b), None) = (it.next(), it.next(), it.next()) {
+                adj[a ^ 1].push(b);
+                adj[b ^ 1].push(a);
+            }
+        }
+        adj
+    }
+
+    /// Replace literals which are equivalent through binary clauses by one
+    /// representative of their strongly connected component.
+    pub(crate) fn substitute_equivalences(
+        &mut self,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> Result<(), Reason> {
+        let comp = strongly_connected(&self.implication_graph());
+
+        // The literal of smallest variable represents each component, thus
+        // the representative of ¬l is the negation of the one of l.
+        let mut rep = vec![usize::MAX; comp.iter().max().map_or(0, |x| x + 1)];
+        for l in 0..self.literals() {
+            if comp[l] == comp[l ^ 1] {
+                self.add_row(core::iter::empty());
+                return Ok(());
+            }
+            if rep[comp[l]] == usize::MAX {
+                rep[comp[l]] = l;
+            }
+        }
+
+        let pairs: Vec<(isize, isize)> = (0..self.literals())
+            .step_by(2)
+            .filter(|&l| rep[comp[l]] != l && !self.2.contains(l) && !self.2.contains(l ^ 1))
+            .map(|l| (self.literal(l), self.literal(rep[comp[l]])))
+            .collect();
+
+        for (l, r) in pairs {
+            budget.check()?;
+            rec.push(l, vec![l, -r]);
+            rec.push(-l, vec![-l, r]);
+            let (from, to) = (self.column(l).unwrap(), self.column(r).unwrap());
+            self.merge_columns(from, to);
+            budget.stats.substituted += 1;
+        }
+        Ok(())
+    }
+
+    /// Replace literal `from` by `to` (and their negations) in all clauses,
+    /// delete resulting tautologies and the variable of `from`.
+    fn merge_columns(&mut self, from: usize, to: usize) {
+        let mut to_delete = self.buffer();
+        let mut tmp = self.buffer();
+        for (f, t) in [(from, to), (from ^ 1, to ^ 1)] {
+            tmp.extend(iter_ones_slice_usize(self.0.col_data(f)));
+            for &i in &tmp {
+                self.0.unset(i, f);
+                if self.0.read(i, t ^ 1) {
+                    to_delete.push(i);
+                } else {
+                    self.0.set(i, t);
+                }
+            }
+            tmp.clear();
+        }
+
+        to_delete.sort_unstable();
+        to_delete.iter().rev().for_each(|&i| self.del_clause(i));
+        self.del_variable(from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, sorted, unlimited};
+    use crate::solver::tests::{fired, nothing, samples};
+
+    #[test]
+    fn substitution() {
+        let equivalences = Preprocessing {
+            equivalences: true,
+            ..nothing()
+        };
+        let x = fired(equivalences, &samples(), |x| x.substituted);
+        assert_eq!(x, (true, true));
+    }
+
+    #[test]
+    fn equivalent_pair() {
+        // 1 ↔ 2, the smaller variable stays.
+        let clauses = [vec![-1, 2], vec![1, -2], vec![1, 3, 4], vec![-2, -3, 4]];
+        let mut x = problem(&clauses);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            x.substitute_equivalences(budget, &mut rec).unwrap();
+            budget.stats
+        });
+        assert_eq!(stats.substituted, 1);
+        assert_eq!(sorted(x.to_vec()), [vec![-3, -1, 4], vec![1, 3, 4]]);
+        assert_eq!(rec.to_string(), "2 2 -1 0\n-2 -2 1 0\n");
+
+        let mut model = vec![1, -2, -3, -4];
+        rec.extend(&mut model);
+        assert_eq!(model, [1, 2, -3, -4]);
+    }
+}
@@ -0,0 +1,177 @@
+use super::Problem;
+use super::equiv::strongly_connected;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::Budget;
+
+use core::alloc::Allocator;
+use core::cmp::Ordering;
+
+/// Longer clauses keep a formula from being renamed into a Horn formula.
+const RENAMING_BOUND: usize = 1 << 4;
+
+/// A model of the 2-CNF with implication graph `adj` as the true literal of
+/// each variable, `None` if a literal implies its negation. Of two literals
+/// the one whose component comes first in reverse topological order is true.
+fn two_sat(adj: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let comp = strongly_connected(adj);
+    (0..adj.len())
+        .step_by(2)
+        .map(|l| match comp[l].cmp(&comp[l + 1]) {
+            Ordering::Less => Some(l),
+            Ordering::Greater => Some(l + 1),
+            Ordering::Equal => None,
+        })
+        .collect()
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Solve a 2-CNF or (renamable) Horn formula without unit clauses in
+    /// linear time and push a model onto `trail`. `None` for other formulas.
+    pub(super) fn solve_fragment(
+        &self,
+        budget: &mut Budget,
+        trail: &mut Vec<isize>,
+    ) -> Option<bool> {
+        if !self.2.is_empty() {
+            return None;
+        }
+        let len = |i| iter_ones_slice_usize(self.0.row_data(i)).len();
+        let model = match (0..self.0.rows()).all(|i| len(i) <= 2) {
+            true => {
+                budget.stats.two_sat += 1;
+                two_sat(&self.implication_graph())
+            }
+            false => {
+                let default = self.horn_renaming()?;
+                budget.stats.horn += 1;
+                self.horn_model(&default)
+            }
+        };
+
+        match model {
+            Some(x) => trail.extend(x.into_iter().map(|l| self.literal(l))),
+            None => return Some(false),
+        }
+        Some(true)
+    }
+
+    /// The literal of each variable s.t. each clause has at most one other
+    /// literal, i.e. the formula is Horn after renaming the variables whose
+    /// positive literal is picked. Negative literals are tried first.
+    fn horn_renaming(&self) -> Option<Vec<usize>> {
+        let horn = (0..self.0.rows()).all(|i| {
+            let x = iter_ones_slice_usize(self.0.row_data(i));
+            x.filter(|&l| l & 1 == 0).nth(1).is_none()
+        });
+        if horn {
+            return Some((0..self.literals()).skip(1).step_by(2).collect());
+        }
+
+        // Picking l means that the others are negated, thus two literals of
+        // a clause are not both left out: a clause (a ∨ b) for each pair.
+        let mut adj = vec![Vec::new(); self.literals()];
+        for i in 0..self.0.rows() {
+            let row: Vec<usize> = iter_ones_slice_usize(self.0.row_data(i)).collect();
+            if row.len() > RENAMING_BOUND {
+                return None;
+            }
+            for (j, &a) in row.iter().enumerate() {
+                for &b in &row[j + 1..] {
+                    adj[a ^ 1].push(b);
+                    adj[b ^ 1].push(a);
+                }
+            }
+        }
+        two_sat(&adj)
+    }
+
+    /// The minimal model w.r.t. the literals `default` of a formula which is
+    /// Horn for them, by unit resolution. Once the default literals of a
+    /// clause are all false, its other literal turns true.
+    fn horn_model(&self, default: &[usize]) -> Option<Vec<usize>> {
+        let mut value = default.to_vec();
+        let mut count: Vec<usize> = (0..self.0.rows())
+            .map(|i| {
+                let x = iter_ones_slice_usize(self.0.row_data(i));
+                x.filter(|&l| default[l >> 1] == l).count()
+            })
+            .collect();
+        let mut queue: Vec<usize> = (0..self.0.rows()).filter(|&i| count[i] == 0).collect();
+
+        while let Some(i) = queue.pop() {
+            let mut x = iter_ones_slice_usize(self.0.row_data(i));
+            let head = x.find(|&l| default[l >> 1] != l)?;
+            if value[head >> 1] == head {
+                continue;
+            }
+            value[head >> 1] = head;
+            for c in iter_ones_slice_usize(self.0.col_data(head ^ 1)) {
+                count[c] -= 1;
+                if count[c] == 0 {
+                    queue.push(c);
+                }
+            }
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::problem::tests::{problem, unlimited};
+    use crate::solver::tests::{fired, nothing, random, samples};
+
+    fn model(clauses: &[Vec<isize>]) -> Option<Vec<isize>> {
+        let x = problem(clauses);
+        let mut trail = Vec::new();
+        let sat = unlimited(|budget| x.solve_fragment(budget, &mut trail))?;
+        sat.then_some(trail)
+    }
+
+    #[test]
+    fn two_sat() {
+        // 1 → 2 → 3 → ¬1 → 4 → 5 → 1, which no unit refutes.
+        let mut formulas = samples();
+        formulas.push((
+            5,
+            [[-1, 2], [-2, 3], [-3, -1], [1, 4], [-4, 5], [-5, 1]]
+                .map(|x| x.to_vec())
+                .to_vec(),
+        ));
+        assert_eq!(fired(nothing(), &formulas, |x| x.two_sat), (true, true));
+    }
+
+    #[test]
+    fn horn() {
+        // Units are propagated first and the rest is satisfied by setting
+        // all variables false, so Horn formulas only turn up satisfiable.
+        let formulas: Vec<_> = (0..10)
+            .map(|seed| {
+                let mut clauses = random(seed, 10, 30, 3);
+                for c in &mut clauses {
+                    c.iter_mut().for_each(|l| *l = -l.abs());
+                    c[0] = -c[0];
+                }
+                clauses.retain(|x| x.iter().all(|l| !x.contains(&-l)));
+                (10, clauses)
+            })
+            .collect();
+        assert!(fired(nothing(), &formulas, |x| x.horn).0);
+    }
+
+    #[test]
+    fn exact_models() {
+        // 1 ∨ 2, ¬1 ∨ ¬2 and 1 ∨ ¬2 only leave 1 true and 2 false.
+        let two_sat = [
+            vec![1, 2],
+            vec![-1, -2],
+            vec![1, -2],
+            vec![2, 3],
+            vec![-3, 1],
+        ];
+        assert_eq!(model(&two_sat), Some(vec![1, -2, 3]));
+        // Horn after renaming 2, whose default is then true.
+        let horn = [vec![-1, 2, 3], vec![-3, -1], vec![-4, -2], vec![4, 2, -3]];
+        assert_eq!(model(&horn), Some(vec![-1, 2, -3, -4]));
+    }
+}
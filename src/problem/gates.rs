@@ -0,0 +1,192 @@
+use super::Problem;
+use super::xor::{XOR_BOUND, xor_signs};
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::circuit::{Circuit, Gate, GateKind};
+
+/// A gate found in the clauses, with the rows of its definition.
+pub(super) type Definition = (Gate, Vec<usize>);
+
+use core::alloc::Allocator;
+
+/// Longer clauses are not taken as the definition of an AND gate.
+const GATE_BOUND: usize = 1 << 4;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// The row of the clause of exactly the sorted columns `literals`.
+    pub(super) fn find_clause(&self, literals: &[usize]) -> Option<usize> {
+        iter_ones_slice_usize(self.0.col_data(literals[0]))
+            .find(|&i| iter_ones_slice_usize(self.0.row_data(i)).eq(literals.iter().copied()))
+    }
+
+    /// The rows and other literals of the clauses with `len` literals over `literal`.
+    fn clauses_with(&self, literal: usize, len: usize) -> Vec<(usize, Vec<usize>)> {
+        iter_ones_slice_usize(self.0.col_data(literal))
+            .filter(|&i| iter_ones_slice_usize(self.0.row_data(i)).len() == len)
+            .map(|i| {
+                let x = iter_ones_slice_usize(self.0.row_data(i)).filter(|&l| l != literal);
+                (i, x.collect())
+            })
+            .collect()
+    }
+
+    /// The rows of the clauses with the literals of each item, if all exist.
+    fn find_all<const N: usize>(&self, clauses: &[[usize; N]]) -> Option<Vec<usize>> {
+        clauses
+            .iter()
+            .map(|x| {
+                let mut x = *x;
+                x.sort_unstable();
+                self.find_clause(&x)
+            })
+            .collect()
+    }
+
+    /// `output` ↔ a₁ ∧ … ∧ aₖ by clauses (¬output ∨ aᵢ) and (output ∨ ¬a₁ ∨ … ∨ ¬aₖ).
+    fn and_gate(&self, output: usize) -> Option<Definition> {
+        let binaries = self.clauses_with(output ^ 1, 2);
+        'clauses: for i in iter_ones_slice_usize(self.0.col_data(output)) {
+            let mut rows = vec![i];
+            let mut inputs = Vec::new();
+            for l in iter_ones_slice_usize(self.0.row_data(i)).filter(|&l| l != output) {
+                match binaries.iter().find(|x| x.1[0] == l ^ 1) {
+                    Some((j, _)) => rows.push(*j),
+                    None => continue 'clauses,
+                }
+                inputs.push(l ^ 1);
+            }
+            if (2..=GATE_BOUND).contains(&inputs.len()) {
+                return Some(self.definition(GateKind::And, output, &inputs, rows));
+            }
+        }
+        None
+    }
+
+    /// `output` ↔ a₁ ⊕ … ⊕ aₖ by a complete block of clauses, which defines
+    /// any of its variables. The one of `literal` is taken as output.
+    fn xor_gate(&self, literal: usize) -> Option<Definition> {
+        for i in iter_ones_slice_usize(self.0.col_data(literal)) {
+            let lits: Vec<usize> = iter_ones_slice_usize(self.0.row_data(i)).collect();
+            if !(3..=XOR_BOUND).contains(&lits.len())
+                || lits.windows(2).any(|x| x[0] >> 1 == x[1] >> 1)
+            {
+                continue;
+            }
+            let parity = lits.iter().filter(|&&l| l & 1 == 1).count() & 1 == 0;
+            let rows: Option<Vec<usize>> = xor_signs(lits.len(), parity)
+                .map(|s| {
+                    let x = lits
+                        .iter()
+                        .enumerate()
+                        .map(|(j, &l)| (l & !1) | (s >> j & 1));
+                    self.find_clause(&x.collect::<Vec<_>>())
+                })
+                .collect();
+            if let Some(rows) = rows {
+                let inputs: Vec<usize> = lits
+                    .iter()
+                    .map(|&l| l & !1)
+                    .filter(|&l| l != literal & !1)
+                    .collect();
+                let output = (literal & !1) | parity as usize;
+                return Some(self.definition(GateKind::Xor, output, &inputs, rows));
+            }
+        }
+        None
+    }
+
+    /// `output` ↔ (c ? t : e) by clauses (¬output ∨ ¬c ∨ t), (¬output ∨ c ∨ e),
+    /// (output ∨ ¬c ∨ ¬t) and (output ∨ c ∨ ¬e).
+    fn ite_gate(&self, output: usize) -> Option<Definition> {
+        let ternaries = self.clauses_with(output ^ 1, 3);
+        for (_, p) in &ternaries {
+            for (_, q) in &ternaries {
+                for (x, t) in [(p[0], p[1]), (p[1], p[0])] {
+                    let c = x ^ 1;
+                    if !q.contains(&c) {
+                        continue;
+                    }
+                    let e = if q[0] == c { q[1] } else { q[0] };
+                    if t >> 1 == e >> 1 || t >> 1 == c >> 1 || e >> 1 == c >> 1 {
+                        continue;
+                    }
+                    let clauses = [
+                        [output ^ 1, c ^ 1, t],
+                        [output ^ 1, c, e],
+                        [output, c ^ 1, t ^ 1],
+                        [output, c, e ^ 1],
+                    ];
+                    if let Some(rows) = self.find_all(&clauses) {
+                        return Some(self.definition(GateKind::Ite, output, &[c, t, e], rows));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// `output` ↔ at least two of a, b, c by the six ternary clauses.
+    fn majority_gate(&self, output: usize) -> Option<Definition> {
+        let ternaries = self.clauses_with(output ^ 1, 3);
+        for (_, p) in &ternaries {
+            for (_, q) in &ternaries {
+                let (a, b) = (p[0], p[1]);
+                if !q.contains(&a) {
+                    continue;
+                }
+                let c = if q[0] == a { q[1] } else { q[0] };
+                if c >> 1 == b >> 1 {
+                    continue;
+                }
+                let clauses = [
+                    [output ^ 1, a, b],
+                    [output ^ 1, a, c],
+                    [output ^ 1, b, c],
+                    [output, a ^ 1, b ^ 1],
+                    [output, a ^ 1, c ^ 1],
+                    [output, b ^ 1, c ^ 1],
+                ];
+                if let Some(rows) = self.find_all(&clauses) {
+                    return Some(self.definition(GateKind::Majority, output, &[a, b, c], rows));
+                }
+            }
+        }
+        None
+    }
+
+    fn definition(
+        &self,
+        kind: GateKind,
+        output: usize,
+        inputs: &[usize],
+        rows: Vec<usize>,
+    ) -> Definition {
+        let gate = Gate {
+            output: self.literal(output),
+            kind,
+            inputs: inputs.iter().map(|&l| self.literal(l)).collect(),
+        };
+        (gate, rows)
+    }
+
+    /// A gate whose output is the variable of `literal`. ITE and majority gates
+    /// are self-dual, thus one polarity of the output is enough.
+    pub(super) fn gate_of(&self, literal: usize) -> Option<Definition> {
+        let v = literal & !1;
+        self.and_gate(v)
+            .or_else(|| self.and_gate(v | 1))
+            .or_else(|| self.xor_gate(v))
+            .or_else(|| self.ite_gate(v))
+            .or_else(|| self.majority_gate(v))
+    }
+
+    /// The gates defining variables, at most one each.
+    pub(crate) fn circuit(&self) -> Circuit {
+        let mut res = Circuit::default();
+        for v in 0..self.variables() {
+            if let Some((x, _)) = self.gate_of(v << 1) {
+                res.push(x);
+            }
+        }
+        res
+    }
+}
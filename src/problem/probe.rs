@@ -0,0 +1,108 @@
+use super::Problem;
+use crate::limits::{Budget, Reason};
+use crate::reconstruction::Reconstruction;
+
+use core::alloc::Allocator;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Failed literal probing: propagate both literals of every variable, a
+    /// conflict asserts the opposite literal and literals implied by both are
+    /// asserted as well.
+    pub(crate) fn probe(
+        &mut self,
+        budget: &mut Budget,
+        rec: &mut Reconstruction,
+    ) -> Result<(), Reason> {
+        let mut prop = self.propagator();
+        let mut pos = Vec::new();
+        // Literals of `pos` marked, s.t. looking one up takes constant time.
+        let mut implied = vec![false; self.literals()];
+
+        prop.propagate();
+        for v in 0..self.variables() {
+            if prop.conflict {
+                break;
+            }
+            if prop.value(v << 1).is_some() {
+                continue;
+            }
+            budget.check()?;
+
+            let root = prop.trail.len();
+            prop.assume(v << 1);
+            let pos_ok = prop.propagate();
+            pos.clear();
+            pos.extend_from_slice(&prop.trail[root + 1..]);
+            pos.iter().for_each(|&l| implied[l] = true);
+            prop.backtrack(root);
+
+            prop.assume((v << 1) | 1);
+            let neg_ok = prop.propagate();
+            let both: Vec<usize> = match (pos_ok, neg_ok) {
+                (true, true) => prop.trail[root + 1..]
+                    .iter()
+                    .copied()
+                    .filter(|&l| implied[l])
+                    .collect(),
+                (true, false) => vec![v << 1],
+                (false, true) => vec![(v << 1) | 1],
+                (false, false) => vec![v << 1, (v << 1) | 1],
+            };
+            prop.backtrack(root);
+            pos.iter().for_each(|&l| implied[l] = false);
+
+            budget.stats.failed_literals += (!pos_ok) as u64 + (!neg_ok) as u64;
+            budget.stats.implied_literals += (pos_ok && neg_ok) as u64 * both.len() as u64;
+            both.into_iter().for_each(|l| prop.assume(l));
+            prop.propagate();
+        }
+
+        if prop.conflict {
+            self.add_row(core::iter::empty());
+            return Ok(());
+        }
+
+        let units: Vec<isize> = prop.trail.iter().map(|&l| self.literal(l)).collect();
+        for l in units {
+            rec.push(l, vec![l]);
+            if let Some(x) = self.column(l) {
+                self.resolve(x, budget)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, sorted, unlimited};
+    use crate::solver::tests::{fired, nothing, samples};
+
+    #[test]
+    fn probing() {
+        let probing = Preprocessing {
+            probing: true,
+            ..nothing()
+        };
+        let formulas = samples();
+        let x = fired(probing, &formulas, |x| x.failed_literals);
+        assert_eq!(x, (true, true));
+        assert!(fired(probing, &formulas, |x| x.implied_literals).0);
+    }
+
+    #[test]
+    fn failed_literal() {
+        // 1 → 2 → 3 → ¬1, so 1 fails and ¬1 becomes a unit.
+        let mut x = problem(&[vec![-1, 2], vec![-2, 3], vec![-3, -1], vec![1, 2, 4]]);
+        let mut rec = Reconstruction::new();
+        let stats = unlimited(|budget| {
+            x.probe(budget, &mut rec).unwrap();
+            budget.stats
+        });
+        assert_eq!((stats.failed_literals, stats.implied_literals), (1, 0));
+        assert_eq!(rec.to_string(), "-1 -1 0\n");
+        assert_eq!(sorted(x.to_vec()), [vec![-2, 3], vec![2, 4]]);
+    }
+}
@@ -0,0 +1,124 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+
+use core::alloc::Allocator;
+use std::mem::take;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Unit propagation over a copy of the clauses, literals are columns.
+    pub(super) fn propagator(&self) -> Propagator {
+        let mut res = Propagator {
+            clauses: Vec::with_capacity(self.0.rows()),
+            watches: vec![Vec::new(); self.literals()],
+            disabled: Vec::with_capacity(self.0.rows()),
+            rows: Vec::with_capacity(self.0.rows()),
+            values: vec![None; self.variables()],
+            trail: Vec::new(),
+            head: 0,
+            conflict: false,
+        };
+
+        for i in 0..self.0.rows() {
+            let c: Vec<usize> = iter_ones_slice_usize(self.0.row_data(i)).collect();
+            match c.len() {
+                0 => res.conflict = true,
+                1 => res.assume(c[0]),
+                _ => {
+                    res.watches[c[0]].push(res.clauses.len());
+                    res.watches[c[1]].push(res.clauses.len());
+                    res.rows.push(i);
+                    res.disabled.push(false);
+                    res.clauses.push(c);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Propagator {
+    pub(super) fn value(&self, literal: usize) -> Option<bool> {
+        self.values[literal >> 1].map(|x| x ^ (literal & 1 == 1))
+    }
+
+    /// Make `literal` true, conflicts are found by `propagate`.
+    pub(super) fn assume(&mut self, literal: usize) {
+        match self.value(literal) {
+            Some(true) => (),
+            Some(false) => self.conflict = true,
+            None => {
+                self.values[literal >> 1] = Some(literal & 1 == 0);
+                self.trail.push(literal);
+            }
+        }
+    }
+
+    /// Returns `false` on conflict (two watched literals).
+    pub(super) fn propagate(&mut self) -> bool {
+        while !self.conflict && self.head < self.trail.len() {
+            let f = self.trail[self.head] ^ 1;
+            self.head += 1;
+
+            let mut ws = take(&mut self.watches[f]);
+            let mut i = 0;
+            while i < ws.len() {
+                let c = ws[i];
+                if self.disabled[c] {
+                    i += 1;
+                    continue;
+                }
+                if self.clauses[c][0] == f {
+                    self.clauses[c].swap(0, 1);
+                }
+                let first = self.clauses[c][0];
+                if self.value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                let len = self.clauses[c].len();
+                if let Some(k) = (2..len).find(|&k| self.value(self.clauses[c][k]) != Some(false)) {
+                    self.clauses[c].swap(1, k);
+                    self.watches[self.clauses[c][1]].push(c);
+                    ws.swap_remove(i);
+                    continue;
+                }
+
+                i += 1;
+                self.assume(first);
+                if self.conflict {
+                    break;
+                }
+            }
+            self.watches[f] = ws;
+        }
+        !self.conflict
+    }
+
+    /// Let clause `clause` be ignored by `propagate`.
+    pub(super) fn disable(&mut self, clause: usize, value: bool) {
+        self.disabled[clause] = value;
+    }
+
+    /// Undo all assignments after the first `len` of the trail.
+    pub(super) fn backtrack(&mut self, len: usize) {
+        for &l in &self.trail[len..] {
+            self.values[l >> 1] = None;
+        }
+        self.trail.truncate(len);
+        self.head = len;
+        self.conflict = false;
+    }
+}
+
+pub(super) struct Propagator {
+    clauses: Vec<Vec<usize>>,
+    watches: Vec<Vec<usize>>,
+    disabled: Vec<bool>,
+    /// The rows the clauses were copied from, units are not kept as clauses.
+    pub(super) rows: Vec<usize>,
+    values: Vec<Option<bool>>,
+    pub(super) trail: Vec<usize>,
+    head: usize,
+    pub(super) conflict: bool,
+}
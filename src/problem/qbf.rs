@@ -0,0 +1,309 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::dimacs::Block;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+
+impl Prefix {
+    /// Levels counted from the outside, where `blocks` alternate. Variables up
+    /// to `variables` not in a block are free, i.e. outermost existential.
+    pub(crate) fn new(blocks: &[Block], variables: usize) -> Self {
+        let mut levels = vec![(0, false); variables];
+        let mut level = 0;
+        let mut universal = false;
+        for b in blocks {
+            if b.universal != universal {
+                level += 1;
+                universal = b.universal;
+            }
+            for &v in &b.variables {
+                levels[v - 1] = (level, universal);
+            }
+        }
+        Self {
+            levels,
+            innermost: level + 1,
+        }
+    }
+
+    /// Variables after the bound ones, like fresh ones, are innermost
+    /// existential.
+    fn level(&self, var: usize) -> usize {
+        self.levels.get(var - 1).map_or(self.innermost, |x| x.0)
+    }
+
+    fn is_universal(&self, var: usize) -> bool {
+        self.levels.get(var - 1).is_some_and(|x| x.1)
+    }
+
+    /// Whether `var` is existential in the outermost block, s.t. its value in
+    /// a model witnesses the truth of the formula.
+    pub(crate) fn is_outermost(&self, var: usize) -> bool {
+        self.levels.get(var - 1).is_some_and(|&x| x == (0, false))
+    }
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Drop the universal literals of each clause quantified inside all of
+    /// its existential literals, they cannot help to satisfy it.
+    fn reduce_universals(&mut self, prefix: &Prefix, budget: &mut Budget) {
+        let mut tmp = self.buffer();
+        for i in 0..self.0.rows() {
+            let row = self.0.row_data(i);
+            let inner = iter_ones_slice_usize(row)
+                .map(|l| self.1[l >> 1])
+                .filter(|&v| !prefix.is_universal(v))
+                .map(|v| prefix.level(v))
+                .max();
+            tmp.extend(iter_ones_slice_usize(row).filter(|&l| {
+                let v = self.1[l >> 1];
+                prefix.is_universal(v) && Some(prefix.level(v)) > inner
+            }));
+            budget.stats.universal_reduced += tmp.len() as u64;
+            tmp.drain(..).for_each(|l| self.0.flip(i, l));
+        }
+    }
+
+    /// Pure existential literals are true and pure universal ones false, the
+    /// values of the former are pushed onto `trail`. Returns whether any was
+    /// found.
+    fn remove_pure_quantified(
+        &mut self,
+        prefix: &Prefix,
+        budget: &mut Budget,
+        trail: &mut Vec<isize>,
+    ) -> Result<bool, Reason> {
+        let mut found = false;
+        let mut i = 0;
+        while i < self.literals() {
+            let Some(l) = self.pure(i) else {
+                i += 2;
+                continue;
+            };
+            // The last variable takes the columns of the resolved one.
+            if prefix.is_universal(self.1[i >> 1]) {
+                self.resolve(l ^ 1, budget)?;
+            } else {
+                trail.push(self.literal(l));
+                self.resolve(l, budget)?;
+            }
+            budget.stats.pure_literals += 1;
+            found = true;
+        }
+        Ok(found)
+    }
+
+    /// Decide the QBF of `prefix` over the clauses by QDPLL: branch on the
+    /// variables of the outermost block left, where both values must succeed
+    /// for universal ones. If true, `trail` holds the values of the outermost
+    /// existential variables as far as they matter.
+    pub(crate) fn solve_qbf(
+        mut self,
+        prefix: &Prefix,
+        budget: &mut Budget,
+        trail: &mut Vec<isize>,
+        depth: usize,
+    ) -> Result<bool, Reason> {
+        budget.node(depth)?;
+
+        // Unit clauses are existential after universal reduction.
+        loop {
+            self.reduce_universals(prefix, budget);
+            if (0..self.0.rows()).any(|i| self.0.row_data(i).iter().all(|&x| x == 0)) {
+                budget.conflict()?;
+                return Ok(false);
+            }
+            if let Some(x) = self.unit() {
+                trail.push(self.literal(x));
+                self.resolve(x, budget)?;
+            } else if !self.remove_pure_quantified(prefix, budget, trail)? {
+                break;
+            }
+        }
+        if self.0.rows() == 0 {
+            return Ok(true);
+        }
+        let len = trail.len();
+
+        // Components share no variable, so the formula is their conjunction.
+        let (of, n) = self.components();
+        if n > 1 {
+            budget.stats.components += n as u64;
+            for c in 0..n {
+                if !self
+                    .component(&of, c)
+                    .solve_qbf(prefix, budget, trail, depth)?
+                {
+                    trail.truncate(len);
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        let vars = (0..self.variables()).map(|i| prefix.level(self.1[i]));
+        let Some(level) = vars.min() else {
+            return Ok(true);
+        };
+        let Some(choice) = self.choose_in(|l| prefix.level(self.1[l >> 1]) == level) else {
+            return Ok(true);
+        };
+        let universal = prefix.is_universal(self.1[choice >> 1]);
+        let mut cpy = self.clone();
+
+        budget.decision()?;
+        trail.push(self.literal(choice));
+        self.resolve(choice, budget)?;
+        let first = self.solve_qbf(prefix, budget, trail, depth + 1)?;
+        // A true existential or a false universal branch decides.
+        if first != universal {
+            if universal {
+                trail.truncate(len);
+            }
+            return Ok(first);
+        }
+        trail.truncate(len);
+
+        trail.push(cpy.literal(choice ^ 1));
+        cpy.resolve(choice ^ 1, budget)?;
+        let second = cpy.solve_qbf(prefix, budget, trail, depth + 1)?;
+        if universal || !second {
+            trail.truncate(len);
+        }
+        Ok(second)
+    }
+}
+
+/// The quantifier level of each variable and whether it is universal.
+pub(crate) struct Prefix {
+    levels: Vec<(usize, bool)>,
+    innermost: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dimacs::Block;
+    use crate::limits::Limits;
+    use crate::solver::tests::{random, solver};
+    use crate::solver::{Solution, SolverError};
+    use crate::statistics::Statistics;
+
+    fn block(universal: bool, variables: &[usize]) -> Block {
+        Block {
+            universal,
+            variables: variables.to_vec(),
+        }
+    }
+
+    /// The witness of a true QBF, `None` for a false one.
+    fn qbf(prefix: &[Block], clauses: &[Vec<isize>]) -> (Option<Vec<isize>>, Statistics) {
+        let vars = clauses.iter().flatten().map(|l| l.unsigned_abs()).max();
+        let mut solver = solver(vars.unwrap_or(0), clauses);
+        let witness = match solver.solve_qbf(prefix).unwrap() {
+            Solution::Satisfiable(x) => Some(x),
+            Solution::Unsatisfiable => None,
+            Solution::Unknown(x) => panic!("stopped by {x:?}"),
+        };
+        (witness, *solver.statistics())
+    }
+
+    #[test]
+    fn quantifier_order() {
+        let clauses = [vec![1, 2], vec![-1, -2]];
+        let prefix = [block(true, &[1]), block(false, &[2])];
+        assert_eq!(qbf(&prefix, &clauses).0, Some(vec![]));
+        let prefix = [block(false, &[2]), block(true, &[1])];
+        assert_eq!(qbf(&prefix, &clauses).0, None);
+    }
+
+    #[test]
+    fn universal_reduction() {
+        // ∃1 ∀2, where 2 drops out of the clauses.
+        let prefix = [block(false, &[1]), block(true, &[2])];
+        let (witness, stats) = qbf(&prefix, &[vec![1, 2]]);
+        assert_eq!(witness, Some(vec![1]));
+        assert_eq!(stats.universal_reduced, 1);
+        let (witness, stats) = qbf(&prefix, &[vec![1, 2], vec![-1, 2]]);
+        assert_eq!(witness, None);
+        assert!(stats.universal_reduced > 0);
+    }
+
+    #[test]
+    fn universal_pure_literals() {
+        // ∀1 ∃2 3, where 1 only occurs positively and is set false.
+        let prefix = [block(true, &[1]), block(false, &[2, 3])];
+        let mut clauses = vec![vec![1, 2], vec![1, 3]];
+        let (witness, stats) = qbf(&prefix, &clauses);
+        assert!(witness.is_some() && stats.pure_literals > 0);
+        clauses.push(vec![-2, -3]);
+        let (witness, stats) = qbf(&prefix, &clauses);
+        assert!(witness.is_none() && stats.pure_literals > 0);
+    }
+
+    #[test]
+    fn expansion() {
+        // ∃1 2 ∀3 ∃4 5 compared with expanding the universal variable.
+        let prefix = [
+            block(false, &[1, 2]),
+            block(true, &[3]),
+            block(false, &[4, 5]),
+        ];
+        let (mut true_, mut false_) = (0, 0);
+        for seed in 0..200 {
+            let clauses = random(seed, 5, 8, 3);
+            let holds = |model: &[isize], c: &Vec<isize>| c.iter().any(|l| model.contains(l));
+            let expected = (0..4).any(|outer: usize| {
+                [-3, 3].iter().all(|&u| {
+                    (0..4).any(|inner: usize| {
+                        let model = [
+                            [-1, 1][outer & 1],
+                            [-2, 2][outer >> 1],
+                            u,
+                            [-4, 4][inner & 1],
+                            [-5, 5][inner >> 1],
+                        ];
+                        clauses.iter().all(|c| holds(&model, c))
+                    })
+                })
+            });
+            let (witness, _) = qbf(&prefix, &clauses);
+            assert_eq!(witness.is_some(), expected, "{clauses:?}");
+            let Some(witness) = witness else {
+                false_ += 1;
+                continue;
+            };
+            true_ += 1;
+            // The witness leaves a true formula over the rest.
+            let mut rest = clauses.clone();
+            rest.extend(witness.iter().map(|&l| vec![l]));
+            assert!(qbf(&prefix, &rest).0.is_some());
+        }
+        assert!(true_ > 0 && false_ > 0);
+    }
+
+    #[test]
+    fn unsupported() {
+        let prefix = [block(true, &[1]), block(false, &[2])];
+        let clauses = [vec![1, 2], vec![-1, -2]];
+
+        let mut x = solver(2, &clauses);
+        x.preprocess().unwrap();
+        assert!(matches!(x.solve_qbf(&prefix), Err(SolverError::Simplified)));
+
+        let mut x = solver(2, &clauses);
+        x.add_at_most(1, vec![1, 2]).unwrap();
+        let answer = x.solve_qbf(&prefix);
+        assert!(matches!(answer, Err(SolverError::QuantifiedAtMost)));
+
+        // A stopped search leaves the problem for the QBF.
+        let mut x = solver(2, &clauses);
+        x.set_limits(Limits {
+            nodes: Some(0),
+            ..Limits::default()
+        });
+        assert!(matches!(x.solve(), Ok(Solution::Unknown(_))));
+        x.set_limits(Limits::default());
+        assert!(matches!(x.solve_qbf(&prefix), Ok(Solution::Satisfiable(_))));
+    }
+}
@@ -0,0 +1,176 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+
+/// Relation of clause *C* to clause *D*.
+enum Subsumes {
+    No,
+    /// C ⊆ D.
+    Yes,
+    /// C ∖ {l} ∪ {¬l} ⊆ D, s.t. ¬l can be removed from D.
+    Strengthens(usize),
+}
+
+/// Bloom-style hash of the variables of a clause.
+fn signature(clause: &[usize]) -> u64 {
+    clause.iter().fold(0, |s, &l| s | 1 << ((l >> 1) & 63))
+}
+
+/// Both clauses are sorted by column and free of tautologies.
+fn subsumes(c: &[usize], d: &[usize]) -> Subsumes {
+    let mut res = Subsumes::Yes;
+    let mut j = 0;
+    for &l in c {
+        while j < d.len() && d[j] >> 1 < l >> 1 {
+            j += 1;
+        }
+        match d.get(j) {
+            Some(&x) if x == l => (),
+            Some(&x) if x == l ^ 1 && matches!(res, Subsumes::Yes) => {
+                res = Subsumes::Strengthens(l);
+            }
+            _ => return Subsumes::No,
+        }
+        j += 1;
+    }
+    res
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Backward subsumption and self-subsuming resolution until fixpoint.
+    ///
+    /// Applies the rules of `handle_shrinked` on all clauses, including the
+    /// strengthening `shrink_clause` does, but finds candidates through
+    /// occurrence lists and signatures instead of scanning the columns, and
+    /// deletes subsumed clauses instead of marking them.
+    ///
+    /// The clause copies and occurrence lists grow one by one, which the
+    /// arena could only reclaim after all of them are freed, so they live on
+    /// the global heap and are not counted against `Limits::memory`.
+    pub(crate) fn subsume(&mut self, budget: &mut Budget) -> Result<(), Reason> {
+        let rows = self.0.rows();
+        let mut clauses: Vec<Vec<usize>> = (0..rows)
+            .map(|i| iter_ones_slice_usize(self.0.row_data(i)).collect())
+            .collect();
+        let mut sigs: Vec<u64> = clauses.iter().map(|c| signature(c)).collect();
+
+        let mut occs = vec![Vec::new(); self.variables()];
+        for (i, c) in clauses.iter().enumerate() {
+            c.iter().for_each(|&l| occs[l >> 1].push(i));
+        }
+
+        // Short clauses subsume most, so they are popped first.
+        let mut queue: Vec<usize> = (0..rows).collect();
+        queue.sort_unstable_by_key(|&i| usize::MAX - clauses[i].len());
+        let mut queued = vec![true; rows];
+        let mut deleted = vec![false; rows];
+
+        while let Some(c) = queue.pop() {
+            queued[c] = false;
+            if deleted[c] || clauses[c].is_empty() {
+                continue;
+            }
+            budget.check()?;
+
+            let best = clauses[c]
+                .iter()
+                .map(|&l| l >> 1)
+                .min_by_key(|&v| occs[v].len())
+                .unwrap();
+
+            let mut k = 0;
+            while k < occs[best].len() {
+                let d = occs[best][k];
+                k += 1;
+                if d == c
+                    || deleted[d]
+                    || clauses[d].len() < clauses[c].len()
+                    || sigs[c] & !sigs[d] != 0
+                {
+                    continue;
+                }
+
+                match subsumes(&clauses[c], &clauses[d]) {
+                    Subsumes::No => (),
+                    Subsumes::Yes => {
+                        deleted[d] = true;
+                        budget.stats.subsumed += 1;
+                    }
+                    Subsumes::Strengthens(l) => {
+                        clauses[d].retain(|&x| x != l ^ 1);
+                        sigs[d] = signature(&clauses[d]);
+                        self.0.flip(d, l ^ 1);
+                        budget.stats.strengthened += 1;
+
+                        occs[l >> 1].retain(|&x| x != d);
+                        if l >> 1 == best {
+                            k -= 1;
+                        }
+                        if !queued[d] {
+                            queued[d] = true;
+                            queue.push(d);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut tmp = self.buffer();
+        tmp.extend((0..rows).filter(|&i| deleted[i]));
+        tmp.iter().rev().for_each(|&i| self.del_clause(i));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::problem::tests::{problem, unlimited};
+    use crate::solver::tests::{random, satisfies};
+
+    #[test]
+    fn fixpoint() {
+        let mut strengthened = 0;
+        for seed in 0..300 {
+            let mut clauses = random(seed, 6, 25, 3);
+            clauses.retain(|x| x.iter().all(|l| !x.contains(&-l)));
+
+            let mut x = problem(&clauses);
+            strengthened += unlimited(|budget| x.subsume(budget).map(|_| budget.stats))
+                .unwrap()
+                .strengthened;
+            let rest = x.to_vec();
+
+            // Subsumed clauses stay among the rows, but are implied.
+            let mut y = problem(&clauses);
+            unlimited(|budget| {
+                let mut all = y.buffer();
+                all.extend(0..y.clauses());
+                y.handle_shrinked(all, budget)
+            })
+            .unwrap();
+            let marked = y.to_vec();
+
+            for bits in 0..1 << 6 {
+                let model: Vec<isize> = (1..=6).map(|v| [-v, v][bits >> (v - 1) & 1]).collect();
+                let expected = satisfies(&model, &clauses);
+                assert_eq!(satisfies(&model, &rest), expected);
+                assert_eq!(satisfies(&model, &marked), expected);
+            }
+
+            // An empty clause is not used to subsume the others.
+            if rest.iter().any(|c| c.is_empty()) {
+                continue;
+            }
+            for (i, c) in rest.iter().enumerate() {
+                for (j, d) in rest.iter().enumerate() {
+                    let outside: Vec<_> = c.iter().filter(|l| !d.contains(l)).collect();
+                    let negated = outside.len() == 1 && d.contains(&-outside[0]);
+                    assert!(i == j || !(outside.is_empty() || negated));
+                }
+            }
+        }
+        assert!(strengthened > 0);
+    }
+}
@@ -0,0 +1,275 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+use core::mem::take;
+use std::collections::VecDeque;
+
+/// Search nodes spent on finding generators.
+const SYMMETRY_NODES: usize = 1 << 12;
+
+/// Positions of a generator covered by its lex-leader clauses.
+const LEX_LEADER_BOUND: usize = 1 << 6;
+
+impl Partition {
+    fn new(colors: &[usize]) -> Self {
+        let mut cells = vec![Vec::new(); colors.iter().max().map_or(0, |x| x + 1)];
+        for (v, &c) in colors.iter().enumerate() {
+            cells[c].push(v);
+        }
+        cells.retain(|x| !x.is_empty());
+
+        let mut cell_of = vec![0; colors.len()];
+        for (i, x) in cells.iter().enumerate() {
+            x.iter().for_each(|&v| cell_of[v] = i);
+        }
+        Self { cells, cell_of }
+    }
+
+    /// Sizes of the cells, which agree if a partition maps onto another.
+    fn shape(&self) -> impl Iterator<Item = usize> {
+        self.cells.iter().map(|x| x.len())
+    }
+
+    /// Move `v` into a new singleton cell, which is returned.
+    fn individualize(&mut self, v: usize) -> usize {
+        let c = self.cell_of[v];
+        self.cells[c].retain(|&x| x != v);
+        self.cell_of[v] = self.cells.len();
+        self.cells.push(vec![v]);
+        self.cells.len() - 1
+    }
+
+    /// Split cells by the number of neighbours in each splitter until the
+    /// partition is equitable. New cells are numbered canonically: affected
+    /// cells in order, their parts by increasing count.
+    fn refine<I: IntoIterator<Item = usize>>(&mut self, adj: &[Vec<usize>], splitters: I) {
+        let mut queue: VecDeque<usize> = splitters.into_iter().collect();
+        let mut count = vec![0; self.cell_of.len()];
+        let mut touched = Vec::new();
+        while let Some(s) = queue.pop_front() {
+            for &m in &self.cells[s] {
+                for &w in &adj[m] {
+                    if count[w] == 0 {
+                        touched.push(w);
+                    }
+                    count[w] += 1;
+                }
+            }
+
+            let mut affected: Vec<usize> = touched.iter().map(|&w| self.cell_of[w]).collect();
+            affected.sort_unstable();
+            affected.dedup();
+            for c in affected {
+                let mut members = take(&mut self.cells[c]);
+                members.sort_by_key(|&w| count[w]);
+                let mut parts = members.chunk_by(|&a, &b| count[a] == count[b]);
+                self.cells[c] = parts.next().unwrap().to_vec();
+                let old_len = self.cells.len();
+                for x in parts {
+                    let id = self.cells.len();
+                    x.iter().for_each(|&w| self.cell_of[w] = id);
+                    self.cells.push(x.to_vec());
+                    queue.push_back(id);
+                }
+                if self.cells.len() > old_len {
+                    queue.push_back(c);
+                }
+            }
+
+            touched.drain(..).for_each(|w| count[w] = 0);
+        }
+    }
+}
+
+impl Search<'_> {
+    /// Follow the first path from `level` on, trying every vertex of the
+    /// target cell, and return the automorphism found at a leaf.
+    fn search(&mut self, level: usize, right: Partition) -> Option<Vec<usize>> {
+        if !right.shape().eq(self.path[level].shape()) {
+            return None;
+        }
+        if level == self.chosen.len() {
+            let mut perm = vec![0; right.cell_of.len()];
+            for (x, y) in self.path[level].cells.iter().zip(&right.cells) {
+                perm[x[0]] = y[0];
+            }
+            let is_automorphism = (0..perm.len()).all(|v| {
+                self.adj[v]
+                    .iter()
+                    .all(|&w| self.adj[perm[v]].binary_search(&perm[w]).is_ok())
+            });
+            return is_automorphism.then_some(perm);
+        }
+
+        if self.nodes == 0 {
+            return None;
+        }
+        self.nodes -= 1;
+
+        let t = self.chosen[level];
+        for &u in &right.cells[t] {
+            let mut next = right.clone();
+            let c = next.individualize(u);
+            next.refine(self.adj, [c, t]);
+            if let Some(x) = self.search(level + 1, next) {
+                return Some(x);
+            }
+        }
+        None
+    }
+}
+
+/// Representative of the orbit of `v`.
+fn find(orbit: &mut [usize], mut v: usize) -> usize {
+    while orbit[v] != v {
+        orbit[v] = orbit[orbit[v]];
+        v = orbit[v];
+    }
+    v
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// The colored graph of literals, clauses and at-most constraints, s.t.
+    /// its automorphisms permute variables and preserve the formula.
+    fn symmetry_graph(&self) -> (Vec<Vec<usize>>, Vec<usize>) {
+        let mut adj = vec![Vec::new(); self.literals()];
+        let mut colors: Vec<usize> = (0..self.literals()).map(|l| l & 1).collect();
+        for l in (0..self.literals()).step_by(2) {
+            adj[l].push(l + 1);
+            adj[l + 1].push(l);
+        }
+
+        let clauses = (0..self.0.rows()).map(|i| (2, self.0.row_data(i)));
+        let at_most = self.2.constraints().map(|(k, x)| (3 + k, x));
+        for (color, row) in clauses.chain(at_most) {
+            let v = adj.len();
+            adj.push(iter_ones_slice_usize(row).collect());
+            for l in iter_ones_slice_usize(row) {
+                adj[l].push(v);
+            }
+            colors.push(color);
+        }
+        adj.iter_mut().for_each(|x| x.sort_unstable());
+        (adj, colors)
+    }
+
+    /// Find generators of the automorphism group of the formula by partition
+    /// refinement and add lex-leader clauses for each of them, numbering
+    /// fresh variables after `vars`.
+    pub(crate) fn break_symmetries(
+        &mut self,
+        budget: &mut Budget,
+        vars: &mut usize,
+    ) -> Result<(), Reason> {
+        let (adj, colors) = self.symmetry_graph();
+        let mut root = Partition::new(&colors);
+        root.refine(&adj, 0..root.cells.len());
+
+        // The first path, individualizing the least vertex of the first
+        // non-singleton cell.
+        let mut path = vec![root];
+        let (mut chosen, mut picked) = (Vec::new(), Vec::new());
+        while let Some(t) = path.last().unwrap().cells.iter().position(|x| x.len() > 1) {
+            budget.check()?;
+            let mut next = path.last().unwrap().clone();
+            let v = *next.cells[t].iter().min().unwrap();
+            let c = next.individualize(v);
+            next.refine(&adj, [c, t]);
+            chosen.push(t);
+            picked.push(v);
+            path.push(next);
+        }
+
+        // Generators of the stabilizers along the path, deepest first.
+        let mut orbit: Vec<usize> = (0..adj.len()).collect();
+        let mut generators = Vec::new();
+        let mut s = Search {
+            adj: &adj,
+            path: &path,
+            chosen: &chosen,
+            nodes: SYMMETRY_NODES,
+        };
+        for level in (0..chosen.len()).rev() {
+            let (t, v) = (chosen[level], picked[level]);
+            for &w in &path[level].cells[t] {
+                if s.nodes == 0 || find(&mut orbit, w) == find(&mut orbit, v) {
+                    continue;
+                }
+                budget.check()?;
+
+                let mut right = path[level].clone();
+                let c = right.individualize(w);
+                right.refine(&adj, [c, t]);
+                if let Some(perm) = s.search(level + 1, right) {
+                    for (x, &y) in perm.iter().enumerate() {
+                        let (a, b) = (find(&mut orbit, x), find(&mut orbit, y));
+                        orbit[a] = b;
+                    }
+                    generators.push(perm);
+                }
+            }
+        }
+
+        // Variables without occurrences are swapped freely, which is not
+        // worth any clause. Fixing them leaves an automorphism.
+        let literals = self.literals();
+        for mut perm in generators {
+            perm.truncate(literals);
+            for l in 0..literals {
+                if adj[l].len() == 1 && adj[l ^ 1].len() == 1 {
+                    perm[l] = l;
+                }
+            }
+            if perm.iter().enumerate().all(|(l, &x)| l == x) {
+                continue;
+            }
+            budget.stats.symmetries += 1;
+            self.add_lex_leader(&perm, vars);
+        }
+        Ok(())
+    }
+
+    /// Clauses admitting only assignments α with α ≤ α ∘ σ lexicographically,
+    /// where σ is the variable permutation of `perm`. Fresh variables eᵢ
+    /// state that the first i positions are equal.
+    fn add_lex_leader(&mut self, perm: &[usize], vars: &mut usize) {
+        let support: Vec<usize> = (0..perm.len())
+            .step_by(2)
+            .filter(|&l| perm[l] != l)
+            .take(LEX_LEADER_BOUND)
+            .collect();
+
+        let mut equal: Option<usize> = None;
+        for (i, &x) in support.iter().enumerate() {
+            let y = perm[x];
+            let prefix = equal.map(|e| e ^ 1);
+            self.add_row(prefix.into_iter().chain([x ^ 1, y]));
+            if i + 1 == support.len() {
+                break;
+            }
+
+            let e = self.fresh_variable(vars);
+            self.add_row(prefix.into_iter().chain([x, y, e]));
+            self.add_row(prefix.into_iter().chain([x ^ 1, y ^ 1, e]));
+            equal = Some(e);
+        }
+    }
+}
+
+/// Ordered partition of the vertices of the symmetry graph.
+#[derive(Clone)]
+struct Partition {
+    cells: Vec<Vec<usize>>,
+    cell_of: Vec<usize>,
+}
+
+/// The first path of the search tree and what is left of the node budget.
+struct Search<'a> {
+    adj: &'a [Vec<usize>],
+    path: &'a [Partition],
+    /// The target cell of each level of the path.
+    chosen: &'a [usize],
+    nodes: usize,
+}
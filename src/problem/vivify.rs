@@ -0,0 +1,104 @@
+use super::Problem;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Vivification: assume the negation of a clause literal by literal and
+    /// propagate the other clauses. Literals found false are removed, a clause
+    /// implied as a whole is deleted.
+    pub(crate) fn vivify(&mut self, budget: &mut Budget) -> Result<(), Reason> {
+        let mut prop = self.propagator();
+        if !prop.propagate() {
+            return Ok(());
+        }
+
+        let mut to_delete = self.buffer();
+        let mut shrinked = self.buffer();
+        let mut kept = self.buffer();
+        for c in 0..prop.rows.len() {
+            let row = prop.rows[c];
+            budget.check()?;
+
+            prop.disable(c, true);
+            let root = prop.trail.len();
+            let mut implied = false;
+            for l in iter_ones_slice_usize(self.0.row_data(row)) {
+                match prop.value(l) {
+                    Some(true) => {
+                        kept.push(l);
+                        implied = true;
+                        break;
+                    }
+                    Some(false) => (),
+                    None => {
+                        kept.push(l);
+                        prop.assume(l ^ 1);
+                        if !prop.propagate() {
+                            implied = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            prop.backtrack(root);
+
+            let len = self.0.row_data(row).iter().map(|x| x.count_ones() as usize);
+            let len = len.sum::<usize>();
+            if kept.is_empty() {
+                self.add_row(core::iter::empty());
+                return Ok(());
+            } else if implied && kept.len() == len {
+                to_delete.push(row);
+                budget.stats.redundant += 1;
+            } else {
+                prop.disable(c, false);
+                if kept.len() < len {
+                    let removed = iter_ones_slice_usize(self.0.row_data(row))
+                        .filter(|l| !kept.contains(l))
+                        .collect::<Vec<_>>();
+                    removed.into_iter().for_each(|l| self.0.flip(row, l));
+                    shrinked.push(row);
+                    budget.stats.vivified += (len - kept.len()) as u64;
+                }
+            }
+            kept.clear();
+        }
+
+        for &i in to_delete.iter().rev() {
+            let last = self.0.rows() - 1;
+            shrinked
+                .iter_mut()
+                .filter(|x| **x == last)
+                .for_each(|x| *x = i);
+            self.del_clause(i);
+        }
+        self.handle_shrinked(shrinked, budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocessing::Preprocessing;
+    use crate::solver::tests::{check, nothing, pigeonhole, random};
+
+    #[test]
+    fn inprocessing() {
+        let preprocessing = Preprocessing {
+            inprocessing: true,
+            ..nothing()
+        };
+        assert!(!check(preprocessing, 20, &pigeonhole(5, 4)).0);
+        let (mut sat, mut unsat, mut vivified) = (0, 0, 0);
+        for seed in 0..60 {
+            let (x, stats) = check(preprocessing, 30, &random(seed, 30, 128, 3));
+            match x {
+                true => sat += 1,
+                false => unsat += 1,
+            }
+            vivified += stats.vivified + stats.redundant;
+        }
+        assert!(sat > 0 && unsat > 0 && vivified > 0);
+    }
+}
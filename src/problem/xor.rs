@@ -0,0 +1,330 @@
+use super::Problem;
+use crate::bits::bit_matrix::BitMatrix;
+use crate::bits::bit_tools::iter_ones_slice_usize;
+use crate::limits::{Budget, Reason};
+
+use core::alloc::Allocator;
+use std::collections::BTreeMap;
+
+/// Clause blocks over more variables are not recognized as XOR constraints.
+pub(super) const XOR_BOUND: usize = 5;
+
+/// Sign patterns of the clauses encoding x₁ ⊕ … ⊕ xₖ = `parity`, bit i set
+/// means xᵢ is negated. Each clause excludes one assignment of wrong parity.
+pub(crate) fn xor_signs(k: usize, parity: bool) -> impl Iterator<Item = usize> {
+    (0..1 << k).filter(move |x: &usize| (x.count_ones() & 1 == 1) != parity)
+}
+
+impl<A: Allocator + Copy> Xor<A> {
+    pub(super) fn new_in(a: A) -> Self {
+        Self(BitMatrix::new_in(a), Vec::new_in(a))
+    }
+
+    pub(super) fn push_empty_col(&mut self) {
+        self.0.push_empty_col();
+    }
+
+    pub(super) fn swap_remove_col(&mut self, col: usize) {
+        self.0.swap_remove_col(col);
+    }
+
+    fn push<I: Iterator<Item = usize>>(&mut self, parity: bool, cols: I) {
+        let row = self.0.rows();
+        self.0.push_empty_row();
+        cols.for_each(|c| self.0.set(row, c));
+        self.1.push(parity);
+    }
+
+    fn del(&mut self, row: usize) {
+        self.0.swap_remove_row(row);
+        self.1.swap_remove(row);
+    }
+
+    pub(super) fn clear(&mut self) {
+        while self.0.rows() > 0 {
+            self.del(self.0.rows() - 1);
+        }
+    }
+
+    /// Drop the rows over column `col`.
+    pub(super) fn forget(&mut self, col: usize) {
+        for i in (0..self.0.rows()).rev() {
+            if self.0.read(i, col) {
+                self.del(i);
+            }
+        }
+    }
+
+    /// Keep the rows whose columns satisfy `f`.
+    pub(super) fn retain<F: Fn(&[usize]) -> bool>(&mut self, f: F) {
+        for i in (0..self.0.rows()).rev() {
+            if !f(self.0.row_data(i)) {
+                self.del(i);
+            }
+        }
+    }
+
+    /// Substitute the value of the variable of `literal`, which is true.
+    pub(super) fn assign(&mut self, literal: usize) {
+        let col = literal & !1;
+        let rows: Vec<usize> = iter_ones_slice_usize(self.0.col_data(col)).collect();
+        for i in rows {
+            self.0.unset(i, col);
+            self.1[i] ^= literal & 1 == 0;
+        }
+    }
+}
+
+impl<A: Allocator + Copy> Problem<A> {
+    /// Add the 2ᵏ⁻¹ clauses encoding that the DIMACS variables `vars` sum up
+    /// to `parity`, only valid as long as no variable has been removed.
+    pub(crate) fn add_xor(&mut self, vars: &[usize], parity: bool) {
+        for s in xor_signs(vars.len(), parity) {
+            let literals = vars.iter().enumerate().map(|(i, &v)| {
+                let v = v as isize;
+                if s >> i & 1 == 1 { -v } else { v }
+            });
+            self.add_clause(literals);
+        }
+    }
+
+    /// XOR constraints found as complete clause blocks. Rows are constraints,
+    /// column k stands for variable k and the last column holds the parity.
+    fn xors(&self) -> BitMatrix<A> {
+        // Variables of the block ↦ sign patterns seen, indexed like `xor_signs`.
+        let mut blocks = BTreeMap::<Vec<usize>, u64>::new();
+        for i in 0..self.0.rows() {
+            let literals: Vec<usize> = iter_ones_slice_usize(self.0.row_data(i)).collect();
+            if !(2..=XOR_BOUND).contains(&literals.len())
+                || literals.windows(2).any(|x| x[0] >> 1 == x[1] >> 1)
+            {
+                continue;
+            }
+            let signs = literals
+                .iter()
+                .enumerate()
+                .fold(0, |s, (j, &l)| s | (l & 1) << j);
+            let vars = literals.iter().map(|&l| l >> 1).collect();
+            *blocks.entry(vars).or_default() |= 1 << signs;
+        }
+
+        let mut res = BitMatrix::new_in(*self.allocator());
+        (0..=self.variables()).for_each(|_| res.push_empty_col());
+        for (vars, seen) in blocks {
+            for parity in [false, true] {
+                if xor_signs(vars.len(), parity).all(|s| seen >> s & 1 == 1) {
+                    let row = res.rows();
+                    res.push_empty_row();
+                    vars.iter().for_each(|&v| res.set(row, v));
+                    if parity {
+                        res.set(row, self.variables());
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    /// Gauss-Jordan elimination over GF(2) on the XOR constraints found in
+    /// the clauses, run once before the search. Derived units and
+    /// equivalences are added as clauses, an inconsistent system leaves the
+    /// empty clause only.
+    pub(crate) fn gauss_jordan(&mut self, budget: &mut Budget) -> Result<(), Reason> {
+        let mut xors = self.xors();
+        let parity = self.variables();
+        budget.stats.xors += xors.rows() as u64;
+
+        let mut pivot = vec![false; xors.rows()];
+        let mut changed = vec![false; xors.rows()];
+        for c in 0..parity {
+            budget.check()?;
+            let rows: Vec<usize> = iter_ones_slice_usize(xors.col_data(c)).collect();
+            let Some(&i) = rows.iter().find(|&&i| !pivot[i]) else {
+                continue;
+            };
+            pivot[i] = true;
+            for &j in rows.iter().filter(|&&j| j != i) {
+                xors.xor_row(j, i);
+                changed[j] = true;
+            }
+        }
+
+        for i in (0..xors.rows()).filter(|&i| changed[i]) {
+            let vars: Vec<usize> = iter_ones_slice_usize(xors.row_data(i))
+                .filter(|&c| c != parity)
+                .collect();
+            let odd = xors.read(i, parity);
+            match vars[..] {
+                [] if odd => {
+                    self.set_unsatisfiable();
+                    return Ok(());
+                }
+                [v] => self.add_row([(v << 1) | !odd as usize].into_iter()),
+                [v, w] => {
+                    for s in xor_signs(2, odd) {
+                        self.add_row([(v << 1) | (s & 1), (w << 1) | (s >> 1)].into_iter());
+                    }
+                }
+                _ => continue,
+            }
+            budget.stats.xor_implied += 1;
+        }
+        Ok(())
+    }
+
+    /// Keep the XOR constraints found in the clauses as rows next to them,
+    /// which are implied by the clauses and only help the search.
+    pub(crate) fn keep_xors(&mut self) {
+        let xors = self.xors();
+        let parity = self.variables();
+        for i in 0..xors.rows() {
+            let vars = iter_ones_slice_usize(xors.row_data(i)).filter(|&c| c != parity);
+            self.3.push(xors.read(i, parity), vars.map(|v| v << 1));
+        }
+    }
+
+    /// Gauss-Jordan elimination on the XOR rows, run at every decision level
+    /// of the search with the values assigned so far substituted. Returns
+    /// whether it derived units, which are added as clauses, an inconsistent
+    /// system leaves the empty clause only.
+    pub(super) fn eliminate_xors(&mut self, budget: &mut Budget) -> Result<bool, Reason> {
+        if self.3.0.rows() == 0 {
+            return Ok(false);
+        }
+        let mut pivot = vec![false; self.3.0.rows()];
+        for c in (0..self.literals()).step_by(2) {
+            budget.check()?;
+            let rows: Vec<usize> = iter_ones_slice_usize(self.3.0.col_data(c)).collect();
+            let Some(&i) = rows.iter().find(|&&i| !pivot[i]) else {
+                continue;
+            };
+            pivot[i] = true;
+            for &j in rows.iter().filter(|&&j| j != i) {
+                self.3.0.xor_row(j, i);
+                self.3.1[j] ^= self.3.1[i];
+            }
+        }
+
+        let mut units = false;
+        for i in (0..self.3.0.rows()).rev() {
+            let odd = self.3.1[i];
+            let cols = {
+                let mut x = iter_ones_slice_usize(self.3.0.row_data(i));
+                (x.next(), x.next())
+            };
+            match cols {
+                (None, _) if odd => {
+                    self.set_unsatisfiable();
+                    return Ok(false);
+                }
+                (None, _) => self.3.del(i),
+                (Some(c), None) => {
+                    self.add_row([c | !odd as usize].into_iter());
+                    self.3.del(i);
+                    budget.stats.xor_units += 1;
+                    units = true;
+                }
+                _ => (),
+            }
+        }
+        Ok(units)
+    }
+}
+
+/// XOR constraints kept next to the clauses during the search. Rows are
+/// constraints over the positive literal columns of the problem and their
+/// parities are kept alongside.
+#[derive(Clone)]
+pub(super) struct Xor<A: Allocator + Copy>(BitMatrix<A>, Vec<bool, A>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+    use crate::preprocessing::Preprocessing;
+    use crate::problem::tests::{problem, unlimited};
+    use crate::solver::tests::{check, nothing, satisfies, solver};
+    use crate::solver::{Solution, Solver};
+
+    /// A ring of rows aᵢ ⊕ aᵢ₊₁ ⊕ bᵢ, every variable occurs in two of them.
+    /// The rows with index in `odd` have parity 1, so the system is
+    /// inconsistent iff their number is odd.
+    fn ring(rows: usize, odd: &[usize]) -> Vec<Vec<isize>> {
+        let a = |i: usize| (i % rows + 1) as isize;
+        let b = |i: usize| (rows + i % (rows / 2) + 1) as isize;
+        let mut clauses = Vec::new();
+        for i in 0..rows {
+            let vars = [a(i), a(i + 1), b(i)];
+            for s in xor_signs(3, odd.contains(&i)) {
+                let signs = (0..3).map(|j| [1, -1][s >> j & 1]);
+                clauses.push(vars.iter().zip(signs).map(|(v, s)| v * s).collect());
+            }
+        }
+        clauses
+    }
+
+    fn without_decisions(mut solver: Solver, preprocessing: Preprocessing) -> Solution {
+        solver.set_preprocessing(preprocessing);
+        solver.set_limits(Limits {
+            decisions: Some(0),
+            ..Limits::default()
+        });
+        solver.solve().unwrap()
+    }
+
+    #[test]
+    fn parity_chain() {
+        let xor = Preprocessing {
+            xor: true,
+            ..nothing()
+        };
+        let clauses = ring(20, &[0]);
+        let x = without_decisions(solver(30, &clauses), xor);
+        assert!(matches!(x, Solution::Unsatisfiable));
+        // The search alone needs decisions.
+        let x = without_decisions(solver(30, &clauses), nothing());
+        assert!(matches!(x, Solution::Unknown(_)));
+
+        let (sat, stats) = check(xor, 30, &ring(20, &[0, 7]));
+        assert!(sat && stats.xors == 20);
+        assert!(!check(xor, 30, &ring(20, &[0, 7, 12])).0);
+    }
+
+    #[test]
+    fn rows_in_search() {
+        // Without the elimination before the search, the rows refute an
+        // inconsistent ring at the root.
+        let mut x = problem(&ring(20, &[0]));
+        x.keep_xors();
+        let mut trail = Vec::new();
+        let (sat, stats) = unlimited(|b| (x.solve(b, &mut trail, 0).unwrap(), b.stats));
+        assert!(!sat && stats.decisions == 0);
+
+        // A consistent one is solved without backtracking.
+        let clauses = ring(20, &[0, 7]);
+        let mut x = problem(&clauses);
+        x.keep_xors();
+        let mut trail = Vec::new();
+        let (sat, stats) = unlimited(|b| (x.solve(b, &mut trail, 0).unwrap(), b.stats));
+        assert!(sat && stats.conflicts == 0 && stats.xor_units > 0);
+        let mut model: Vec<isize> = (1..=30).map(|v| -v).collect();
+        trail.iter().for_each(|&l| model[l.unsigned_abs() - 1] = l);
+        assert!(satisfies(&model, &clauses));
+    }
+
+    #[test]
+    fn long_constraints() {
+        // Split up by fresh variables, which the elimination sees through.
+        let xor = Preprocessing {
+            xor: true,
+            ..nothing()
+        };
+        let mut solver = Solver::new(8, 2);
+        solver.add_xor((1..=8).collect()).unwrap();
+        solver
+            .add_xor((1..=8).map(|v| [v, -v][(v == 5) as usize]).collect())
+            .unwrap();
+        let x = without_decisions(solver, xor);
+        assert!(matches!(x, Solution::Unsatisfiable));
+    }
+}
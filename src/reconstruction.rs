@@ -0,0 +1,127 @@
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+impl Reconstruction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty reconstruction of a formula over `variables` variables.
+    pub(crate) fn with_variables(variables: usize) -> Self {
+        Self(Vec::new(), Some(variables))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Variables of the original formula if known, higher ones were added
+    /// by the preprocessing.
+    pub fn original(&self) -> Option<usize> {
+        self.1
+    }
+
+    /// The largest variable mentioned.
+    pub fn variables(&self) -> usize {
+        let lits = self.0.iter().flat_map(|(w, c)| c.iter().chain([w]));
+        lits.map(|l| l.unsigned_abs()).max().unwrap_or(0)
+    }
+
+    /// Remember a removed clause, which `witness` satisfies whenever needed.
+    pub(crate) fn push(&mut self, witness: isize, clause: Vec<isize>) {
+        self.0.push((witness, clause));
+    }
+
+    /// Extend a model of the simplified formula to one of the original, where
+    /// `model[v - 1]` is the literal of variable `v`.
+    pub fn extend(&self, model: &mut [isize]) {
+        for (witness, clause) in self.0.iter().rev() {
+            if !clause.iter().any(|&l| model[l.unsigned_abs() - 1] == l) {
+                model[witness.unsigned_abs() - 1] = *witness;
+            }
+        }
+    }
+}
+
+/// A line `p rec variables` if the original variables are known, then one
+/// line `witness clause 0` per removed clause, in order of removal.
+impl fmt::Display for Reconstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(x) = self.1 {
+            writeln!(f, "p rec {x}")?;
+        }
+        for (witness, clause) in &self.0 {
+            write!(f, "{witness}")?;
+            for l in clause {
+                write!(f, " {l}")?;
+            }
+            writeln!(f, " 0")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads what `Display` writes, empty lines and lines starting with `c` are skipped.
+impl FromStr for Reconstruction {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::new();
+        for line in s
+            .lines()
+            .filter(|x| !x.trim().is_empty() && !x.starts_with('c'))
+        {
+            if let Some(x) = line.strip_prefix("p rec") {
+                res.1 = Some(x.trim().parse()?);
+                continue;
+            }
+            let mut nums = line
+                .split_whitespace()
+                .map(str::parse::<isize>)
+                .collect::<Result<Vec<_>, _>>()?;
+            if nums.last() == Some(&0) {
+                nums.pop();
+            }
+            if let Some((&witness, clause)) = nums.split_first() {
+                res.push(witness, clause.to_vec());
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Removed clauses with their witness literals, in order of removal.
+#[derive(Debug, Clone, Default)]
+pub struct Reconstruction(Vec<(isize, Vec<isize>)>, Option<usize>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut rec = Reconstruction::with_variables(3);
+        rec.push(4, vec![4, -1]);
+        rec.push(-2, vec![-2, 3, 4]);
+        rec.push(1, vec![1]);
+        let text = rec.to_string();
+        assert_eq!(text, "p rec 3\n4 4 -1 0\n-2 -2 3 4 0\n1 1 0\n");
+
+        let read: Reconstruction = format!("c comment\n\n{text}").parse().unwrap();
+        assert_eq!((read.original(), read.variables()), (Some(3), 4));
+        assert_eq!(read.to_string(), text);
+        let mut model = vec![-1, 2, -3, -4];
+        read.extend(&mut model);
+        assert_eq!(model, [1, -2, -3, 4]);
+
+        // Written before the variables were recorded.
+        let read: Reconstruction = "1 1 0\n".parse().unwrap();
+        assert_eq!(read.original(), None);
+        assert!("1 x 0".parse::<Reconstruction>().is_err());
+    }
+}
@@ -1,19 +1,52 @@
 use crate::alloc::StacklikeAlloc;
 use crate::bits::bit_tools::integers_needed;
-use core::alloc::Layout;
+use crate::circuit::Circuit;
+use crate::dimacs::{self, Block, Constraint};
+use crate::limits::{Budget, Callback, Limits, Reason};
+use crate::log::{Logger, Sink, Verbosity, log};
+use crate::statistics::Statistics;
+use crate::terminator::Terminator;
+use core::alloc::{Allocator, Layout};
+use std::alloc::Global;
+use std::io::{self, Write};
+use std::mem::take;
+use std::time::Instant;
 
-use crate::problem::Problem;
+use crate::preprocessing::Preprocessing;
+use crate::problem::{Prefix, Problem};
+use crate::reconstruction::Reconstruction;
 
 use core::iter::Iterator;
 
+/// XOR constraints over more variables are split up.
+const XOR_CHUNK: usize = 4;
+
+/// Bounds of the arena in bytes. The estimate of `Solver::new` grows with
+/// the fourth power of the variables and far exceeds what the matrices take,
+/// the lower bound leaves room for clauses encoding XOR constraints.
+const ARENA_BOUND: usize = 1 << 31;
+const ARENA_MIN: usize = 1 << 20;
+
+fn arena_size(var_numbr: usize, cls_numbr: usize) -> usize {
+    let integers_needed = cls_numbr
+        .saturating_mul(integers_needed(var_numbr))
+        .saturating_mul(integers_needed(var_numbr));
+    integers_needed
+        .saturating_mul(var_numbr)
+        .saturating_mul(var_numbr)
+        .clamp(ARENA_MIN, ARENA_BOUND)
+}
+
 impl Solver {
     pub fn new(var_numbr: usize, cls_numbr: usize) -> Self {
         // let max_steps = usize::min(var_numbr, cls_numbr / 3 + 3);
 
+        Self::with_arena(var_numbr, cls_numbr, arena_size(var_numbr, cls_numbr))
+    }
+
+    fn with_arena(var_numbr: usize, cls_numbr: usize, size: usize) -> Self {
         let allocator = {
-            let integers_needed =
-                cls_numbr * integers_needed(var_numbr) * integers_needed(var_numbr);
-            let layout = Layout::from_size_align(integers_needed * var_numbr * var_numbr, 4096).unwrap();
+            let layout = Layout::from_size_align(size, 4096).unwrap();
             Box::into_raw(Box::new(StacklikeAlloc::new(layout)))
         };
 
@@ -27,74 +60,576 @@ impl Solver {
             allocator,
             var_numbr,
             cls_numbr,
+            cls_added: 0,
+            fresh: 0,
+            simplified: false,
+            input: Vec::new(),
+            at_most: Vec::new(),
+            limits: Limits::default(),
+            terminator: Terminator::new(),
+            callback: None,
+            stats: Statistics::default(),
+            logger: Logger::default(),
+            preprocessing: Preprocessing::default(),
+            reconstruction: Reconstruction::with_variables(var_numbr),
             work_onto,
         }
     }
 
+    /// Bounds every following call to `solve`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Returns a handle which stops `solve` from another thread.
+    pub fn terminator(&self) -> Terminator {
+        self.terminator.clone()
+    }
+
+    /// Install a callback that is polled during `solve` and stops it on `true`.
+    pub fn set_terminate(&mut self, callback: Option<Box<Callback>>) {
+        self.callback = callback;
+    }
+
+    pub fn set_preprocessing(&mut self, preprocessing: Preprocessing) {
+        self.preprocessing = preprocessing;
+    }
+
+    /// Nothing is logged unless both a verbosity and a sink are set.
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.logger.set_verbosity(verbosity);
+    }
+
+    pub fn set_sink(&mut self, sink: Option<Box<dyn Sink>>) {
+        self.logger.set_sink(sink);
+    }
+
+    /// Extends models of the clauses returned by `preprocess`.
+    pub fn reconstruction(&self) -> &Reconstruction {
+        &self.reconstruction
+    }
+
+    /// Counters of the last call to `solve`.
+    pub fn statistics(&self) -> &Statistics {
+        &self.stats
+    }
+
     pub fn need_to_add(&self) -> bool {
         match &self.work_onto {
-            Task::Todo(x) => x.clauses() < self.cls_numbr,
+            Task::Todo(_) => self.cls_added < self.cls_numbr,
             _ => false,
         }
     }
 
+    /// Raise the declared numbers of variables and clauses, s.t. more can be
+    /// added, e.g. between calls to `solve_assuming`. If the arena gets too
+    /// small, or fresh variables of XOR constraints are numbered where the
+    /// new ones go, the constraints are added again to a new problem. Fails
+    /// once `preprocess` ran.
+    pub fn reserve(&mut self, var_numbr: usize, cls_numbr: usize) -> Result<(), SolverError> {
+        match &self.work_onto {
+            Task::Done(_) => return Err(SolverError::AlreadySolved),
+            Task::Todo(_) if self.simplified => return Err(SolverError::Simplified),
+            Task::Todo(_) => {}
+        }
+        let var_numbr = var_numbr.max(self.var_numbr);
+        let cls_numbr = cls_numbr.max(self.cls_numbr);
+        let size = unsafe { self.allocator.as_ref().unwrap() }.size();
+        let needed = arena_size(var_numbr, cls_numbr);
+
+        if needed > size || (var_numbr > self.var_numbr && self.fresh > 0) {
+            // Doubling the arena keeps this rare while the problem grows.
+            let size = match needed > size {
+                true => needed.max(size.saturating_mul(2)).min(ARENA_BOUND),
+                false => size,
+            };
+            let mut x = Self::with_arena(var_numbr, cls_numbr, size);
+            x.limits = self.limits;
+            x.terminator = self.terminator.clone();
+            x.callback = self.callback.take();
+            x.stats = self.stats;
+            x.logger = take(&mut self.logger);
+            x.preprocessing = self.preprocessing;
+            for constraint in take(&mut self.input) {
+                match constraint {
+                    Constraint::Clause(literals) => x.add_clause(literals)?,
+                    Constraint::Xor(literals) => x.add_xor(literals)?,
+                }
+            }
+            for (k, literals) in take(&mut self.at_most) {
+                x.add_at_most(k, literals)?;
+            }
+            *self = x;
+            return Ok(());
+        }
+        self.var_numbr = var_numbr;
+        self.cls_numbr = cls_numbr;
+        self.reconstruction = Reconstruction::with_variables(var_numbr);
+        Ok(())
+    }
+
+    fn check_literals(&self, literals: &[isize]) -> Result<(), SolverError> {
+        if literals.contains(&0) {
+            return Err(SolverError::VariableIsZero);
+        }
+        match literals.iter().find(|x| x.unsigned_abs() > self.var_numbr) {
+            Some(&x) => Err(SolverError::VariableTooLarge(x)),
+            None => Ok(()),
+        }
+    }
+
     pub fn add_clause(&mut self, literals: Vec<isize>) -> Result<(), SolverError> {
+        self.check_literals(&literals)?;
         match &mut self.work_onto {
-            Task::Todo(x) if x.clauses() < self.cls_numbr => {
-                if let Some(_) = literals.iter().find(|&&x| x == 0) {
-                    return Err(SolverError::VariableIsZero);
-                }
-                if let Some(&x) = literals
-                    .iter()
-                    .find(|&&x| x.abs() as usize > self.var_numbr)
-                {
-                    return Err(SolverError::VariableTooLarge(x));
-                }
+            Task::Todo(x) if self.cls_added < self.cls_numbr => {
+                log!(
+                    self.logger,
+                    Verbosity::Trace,
+                    "Adding clause {:?}",
+                    literals
+                );
+
+                x.add_clause(literals.iter().copied());
+                self.input.push(Constraint::Clause(literals));
+                self.cls_added += 1;
+                Ok(())
+            }
+            _ => Err(SolverError::TooManyClauses),
+        }
+    }
+
+    /// Add the constraint that an odd number of `literals` is true, counting
+    /// as one clause. It is stored as clauses, long ones are split up by
+    /// fresh variables, and reasoned about as a parity if `xor` preprocessing
+    /// is enabled.
+    pub fn add_xor(&mut self, literals: Vec<isize>) -> Result<(), SolverError> {
+        self.check_literals(&literals)?;
+        match &mut self.work_onto {
+            Task::Todo(x) if self.cls_added < self.cls_numbr => {
+                log!(self.logger, Verbosity::Trace, "Adding xor {:?}", literals);
 
-                println!("Attempting to add..{:?}", literals);
+                // Variables occuring an odd number of times and their parity.
+                let mut vars = Vec::new();
+                let mut parity = true;
+                for &l in &literals {
+                    parity ^= l < 0;
+                    match vars.iter().position(|&v| v == l.unsigned_abs()) {
+                        Some(i) => _ = vars.swap_remove(i),
+                        None => vars.push(l.unsigned_abs()),
+                    }
+                }
 
-                x.add_clause(literals.into_iter());
+                // x₁ ⊕ x₂ ⊕ x₃ ⊕ t = 0 and t ⊕ x₄ ⊕ … = parity.
+                while vars.len() > XOR_CHUNK {
+                    self.fresh += 1;
+                    let t = self.var_numbr + self.fresh;
+                    x.add_xor(&[vars[0], vars[1], vars[2], t], false);
+                    vars.splice(0..3, [t]);
+                }
+                x.add_xor(&vars, parity);
+                self.input.push(Constraint::Xor(literals));
+                self.cls_added += 1;
                 Ok(())
             }
             _ => Err(SolverError::TooManyClauses),
         }
     }
 
-    pub fn solve(&mut self) -> Result<Solution, SolverError> {
+    /// Add the constraint that at most `k` of `literals` are true, it does not
+    /// count as a clause. Fails once `preprocess` ran.
+    pub fn add_at_most(&mut self, k: usize, literals: Vec<isize>) -> Result<(), SolverError> {
+        self.check_literals(&literals)?;
         match &mut self.work_onto {
-            Task::Done(x) => Ok(x.clone()),
+            Task::Todo(_) if self.simplified => Err(SolverError::Simplified),
             Task::Todo(x) => {
-                if x.clauses() < self.cls_numbr {
+                log!(
+                    self.logger,
+                    Verbosity::Trace,
+                    "Adding at most {} of {:?}",
+                    k,
+                    literals
+                );
+                x.add_at_most(k, &literals);
+                self.at_most.push((k, literals));
+                Ok(())
+            }
+            _ => Err(SolverError::AlreadySolved),
+        }
+    }
+
+    /// The gates defined by the clauses added so far, before simplification.
+    pub fn circuit(&self) -> Result<Circuit, SolverError> {
+        match &self.work_onto {
+            Task::Todo(x) => Ok(x.circuit()),
+            Task::Done(_) => Err(SolverError::AlreadySolved),
+        }
+    }
+
+    /// Write the constraints as added in DIMACS CNF, at-most constraints as
+    /// clauses. The statistics become header comments if `statistics`.
+    pub fn write_input<W: Write>(&self, out: &mut W, statistics: bool) -> Result<(), SolverError> {
+        let mut constraints = self.input.clone();
+        for (k, literals) in &self.at_most {
+            let mut x = Problem::new_in(Global);
+            x.add_at_most(*k, literals);
+            constraints.extend(x.to_vec().into_iter().map(Constraint::Clause));
+        }
+        let comments = self.comments(statistics);
+        dimacs::write(out, self.var_numbr, &constraints, &comments).map_err(SolverError::Io)
+    }
+
+    /// Write the current clauses in DIMACS CNF, simplified once `preprocess`
+    /// ran and with fresh variables numbered after the original ones. The
+    /// statistics become header comments if `statistics`.
+    pub fn write_simplified<W: Write>(
+        &self,
+        out: &mut W,
+        statistics: bool,
+    ) -> Result<(), SolverError> {
+        match &self.work_onto {
+            Task::Todo(x) => {
+                let vars = self.var_numbr + self.fresh;
+                let comments = self.comments(statistics);
+                x.write_dimacs(out, vars, &comments)
+                    .map_err(SolverError::Io)
+            }
+            Task::Done(_) => Err(SolverError::AlreadySolved),
+        }
+    }
+
+    fn comments(&self, statistics: bool) -> String {
+        match statistics {
+            true => self.stats.to_string(),
+            false => String::new(),
+        }
+    }
+
+    /// Only simplify the problem and return the remaining clauses, models of
+    /// them are extended by `reconstruction`. The problem can still be solved.
+    pub fn preprocess(&mut self) -> Result<Vec<Vec<isize>>, SolverError> {
+        match &mut self.work_onto {
+            Task::Done(_) => Err(SolverError::AlreadySolved),
+            Task::Todo(x) => {
+                if self.cls_added < self.cls_numbr {
                     return Err(SolverError::TooFewClauses);
                 }
-                x.prepare();
-                let solution = if x.clone().solve() {
-                    // let mut tmp: Vec<_> = x.guessed.iter_literals().collect();
-                    // tmp.sort_by_key(|x| x.abs());
-                    Solution::Satisfiable(vec![])
-                } else {
-                    Solution::Unsatisfiable
-                };
+                let arena = unsafe { self.allocator.as_ref().unwrap() };
+                let mut budget = Budget::new(
+                    self.limits,
+                    arena,
+                    &self.terminator,
+                    self.callback.as_deref(),
+                );
+
+                // Also if stopped, the problem is changed in place.
+                self.simplified = true;
+                let start = Instant::now();
+                let mut vars = self.var_numbr + self.fresh;
+                let result = simplify(
+                    x,
+                    &self.preprocessing,
+                    &mut budget,
+                    &mut self.reconstruction,
+                    &mut vars,
+                );
+                self.fresh = vars - self.var_numbr;
+                budget.stats.prepare_time = start.elapsed();
+                budget.stats.peak_memory = arena.peak();
+                self.stats = budget.stats;
 
-                dbg!("Attempting to solve..");
+                if let Err(x) = result {
+                    self.answered(x);
+                    return Err(SolverError::Stopped(x));
+                }
+                Ok(x.to_vec())
+            }
+        }
+    }
 
-                self.work_onto = Task::Done(solution.clone());
-                return Ok(solution);
+    /// Search a copy of the problem, s.t. it is left as added, or as
+    /// returned by `preprocess`, if the search is stopped.
+    pub fn solve(&mut self) -> Result<Solution, SolverError> {
+        let x = match &self.work_onto {
+            Task::Done(x) => return Ok(x.clone()),
+            Task::Todo(_) if self.cls_added < self.cls_numbr => {
+                return Err(SolverError::TooFewClauses);
             }
+            Task::Todo(x) => x.clone(),
+        };
+        let solution = self.search(x);
+        // Stay in `Todo` if unknown, s.t. the problem can be solved again.
+        if !matches!(solution, Solution::Unknown(_)) {
+            self.work_onto = Task::Done(solution.clone());
+        }
+        Ok(solution)
+    }
+
+    /// Solve with the `assumptions` as unit clauses, which only hold for this
+    /// call. The problem is kept as added, s.t. more clauses can follow, also
+    /// before all declared ones were added or after `reserve`. Every call simplifies and searches
+    /// a copy from scratch, nothing learned is kept between calls. Fails once
+    /// `preprocess` ran, which may have removed assumed variables.
+    pub fn solve_assuming(&mut self, assumptions: &[isize]) -> Result<Solution, SolverError> {
+        self.check_literals(assumptions)?;
+        let mut x = match &self.work_onto {
+            Task::Todo(_) if self.simplified => return Err(SolverError::Simplified),
+            Task::Todo(x) => x.clone(),
+            Task::Done(_) => return Err(SolverError::AlreadySolved),
+        };
+        log!(self.logger, Verbosity::Debug, "Assuming {:?}", assumptions);
+        assumptions
+            .iter()
+            .for_each(|&l| x.add_clause([l].into_iter()));
+        Ok(self.search(x))
+    }
+
+    /// Decide the QBF of the quantifier `prefix` over the clauses, variables
+    /// not bound by it are outermost existential. A true formula is
+    /// `Satisfiable` with the values of the outermost existential variables,
+    /// which witness it, e.g. solve a 2QBF synthesis problem. Most
+    /// simplifications do not hold under quantifiers, so the problem is only
+    /// prepared. Fails once `preprocess` ran or with at-most constraints.
+    pub fn solve_qbf(&mut self, prefix: &[Block]) -> Result<Solution, SolverError> {
+        if let Task::Done(x) = &self.work_onto {
+            return Ok(x.clone());
         }
+        if self.cls_added < self.cls_numbr {
+            return Err(SolverError::TooFewClauses);
+        }
+        if self.simplified {
+            return Err(SolverError::Simplified);
+        }
+        if !self.at_most.is_empty() {
+            return Err(SolverError::QuantifiedAtMost);
+        }
+        let bound: Vec<isize> = prefix
+            .iter()
+            .flat_map(|x| &x.variables)
+            .map(|&v| v as isize)
+            .collect();
+        self.check_literals(&bound)?;
+        let prefix = Prefix::new(prefix, self.var_numbr);
+
+        let Task::Todo(x) = &self.work_onto else {
+            unreachable!()
+        };
+        let mut x = x.clone();
+        let arena = unsafe { self.allocator.as_ref().unwrap() };
+        let mut budget = Budget::new(
+            self.limits,
+            arena,
+            &self.terminator,
+            self.callback.as_deref(),
+        );
+        let mut trail = Vec::new();
+
+        log!(
+            self.logger,
+            Verbosity::Info,
+            "Preparing {} clauses under quantifiers",
+            x.clauses()
+        );
+        let start = Instant::now();
+        let result = budget.check().and_then(|_| x.prepare(&mut budget));
+        budget.stats.prepare_time = start.elapsed();
+
+        let start = Instant::now();
+        let result = result.and_then(|_| x.solve_qbf(&prefix, &mut budget, &mut trail, 0));
+        budget.stats.search_time = start.elapsed();
+        budget.stats.peak_memory = arena.peak();
+        self.stats = budget.stats;
+
+        let solution = match result {
+            Ok(true) => {
+                let mut model = model(self.var_numbr + self.fresh, trail);
+                model.truncate(self.var_numbr);
+                model.retain(|l| prefix.is_outermost(l.unsigned_abs()));
+                Solution::Satisfiable(model)
+            }
+            Ok(false) => Solution::Unsatisfiable,
+            Err(reason) => {
+                log!(self.logger, Verbosity::Info, "Stopped by {:?}", reason);
+                self.answered(reason);
+                return Ok(Solution::Unknown(reason));
+            }
+        };
+        log!(
+            self.logger,
+            Verbosity::Info,
+            "Searched {} nodes in {}ms",
+            self.stats.nodes,
+            self.stats.search_time.as_millis()
+        );
+        self.work_onto = Task::Done(solution.clone());
+        Ok(solution)
     }
+
+    /// A call stopped by `reason` answers a request of the terminator, which
+    /// is dropped then. A request before the call stops it right away.
+    fn answered(&self, reason: Reason) {
+        if reason == Reason::Terminated {
+            self.terminator.reset();
+        }
+    }
+
+    /// Simplify the copy `x` and search a model of it. Its witnesses are
+    /// extended before those of `preprocess`.
+    fn search(&mut self, mut x: Problem<&'static StacklikeAlloc>) -> Solution {
+        let arena = unsafe { self.allocator.as_ref().unwrap() };
+        let mut budget = Budget::new(
+            self.limits,
+            arena,
+            &self.terminator,
+            self.callback.as_deref(),
+        );
+        budget.inprocessing = self.preprocessing.inprocessing;
+        let mut trail = Vec::new();
+        let mut rec = self.reconstruction.clone();
+        let mut vars = self.var_numbr + self.fresh;
+
+        log!(
+            self.logger,
+            Verbosity::Info,
+            "Preparing {} clauses",
+            x.clauses()
+        );
+        let start = Instant::now();
+        let result = simplify(
+            &mut x,
+            &self.preprocessing,
+            &mut budget,
+            &mut rec,
+            &mut vars,
+        );
+        budget.stats.prepare_time = start.elapsed();
+        log!(
+            self.logger,
+            Verbosity::Debug,
+            "Prepared in {}ms, {} clauses left",
+            budget.stats.prepare_time.as_millis(),
+            x.clauses()
+        );
+
+        let result = result.and_then(|_| match self.preprocessing.symmetry {
+            true => x.break_symmetries(&mut budget, &mut vars),
+            false => Ok(()),
+        });
+
+        // Last, s.t. the rows are implied by the clauses searched.
+        if self.preprocessing.xor {
+            x.keep_xors();
+        }
+
+        let start = Instant::now();
+        let result = result.and_then(|_| x.solve(&mut budget, &mut trail, 0));
+        budget.stats.search_time = start.elapsed();
+        budget.stats.peak_memory = arena.peak();
+        self.stats = budget.stats;
+
+        let solution = match result {
+            Ok(true) => {
+                let mut model = model(vars, trail);
+                rec.extend(&mut model);
+                model.truncate(self.var_numbr);
+                Solution::Satisfiable(model)
+            }
+            Ok(false) => Solution::Unsatisfiable,
+            Err(x) => {
+                log!(self.logger, Verbosity::Info, "Stopped by {:?}", x);
+                self.answered(x);
+                return Solution::Unknown(x);
+            }
+        };
+        log!(
+            self.logger,
+            Verbosity::Info,
+            "Searched {} nodes in {}ms",
+            self.stats.nodes,
+            self.stats.search_time.as_millis()
+        );
+        solution
+    }
+}
+
+/// Run `prepare` and the enabled passes of `preprocessing`, fresh variables
+/// are numbered after `vars`.
+fn simplify<A: Allocator + Copy>(
+    x: &mut Problem<A>,
+    preprocessing: &Preprocessing,
+    budget: &mut Budget,
+    rec: &mut Reconstruction,
+    vars: &mut usize,
+) -> Result<(), Reason> {
+    budget.check()?;
+    // Before subsumption can break up the clause blocks of XOR constraints.
+    if preprocessing.xor {
+        x.gauss_jordan(budget)?;
+    }
+    x.prepare(budget)?;
+    if preprocessing.cardinality {
+        x.detect_at_most(budget)?;
+    }
+    if preprocessing.equivalences {
+        x.substitute_equivalences(budget, rec)?;
+    }
+    if preprocessing.blocked {
+        x.remove_blocked_clauses(budget, rec)?;
+    }
+    if preprocessing.autarky {
+        x.remove_autarkies(budget, rec)?;
+    }
+    if preprocessing.vivification {
+        x.vivify(budget)?;
+    }
+    if preprocessing.probing {
+        x.probe(budget, rec)?;
+    }
+    if preprocessing.elimination {
+        x.eliminate_variables(budget, rec)?;
+    }
+    // After elimination, which would remove the fresh variables again.
+    if preprocessing.addition {
+        x.add_variables(budget, vars)?;
+    }
+    Ok(())
+}
+
+/// Completes `trail` to a model where unassigned variables are false.
+fn model(var_numbr: usize, trail: Vec<isize>) -> Vec<isize> {
+    let mut res: Vec<isize> = (1..=var_numbr as isize).map(|x| -x).collect();
+    for l in trail {
+        res[l.unsigned_abs() - 1] = l;
+    }
+    res
 }
 
 #[derive(Debug, Clone)]
 pub enum Solution {
     Satisfiable(Vec<isize>),
     Unsatisfiable,
+    Unknown(Reason),
 }
 
 pub struct Solver {
     allocator: *mut StacklikeAlloc,
     var_numbr: usize,
     cls_numbr: usize,
+    cls_added: usize,
+    /// Variables after `var_numbr` that are hidden from models.
+    fresh: usize,
+    /// Whether `preprocess` changed the problem, which then only has the
+    /// same satisfiability.
+    simplified: bool,
+    /// The constraints as added, for writing them out again.
+    input: Vec<Constraint>,
+    at_most: Vec<(usize, Vec<isize>)>,
+    limits: Limits,
+    terminator: Terminator,
+    callback: Option<Box<Callback>>,
+    stats: Statistics,
+    logger: Logger,
+    preprocessing: Preprocessing,
+    reconstruction: Reconstruction,
     work_onto: Task<Problem<&'static StacklikeAlloc>, Solution>,
 }
 
@@ -107,14 +642,348 @@ enum Task<A, B> {
 pub enum SolverError {
     VariableIsZero,
     VariableTooLarge(isize),
+    /// More clauses than declared, see `reserve`.
     TooManyClauses,
     TooFewClauses,
+    /// `preprocess`, `circuit` and `write_simplified` need the problem,
+    /// which `solve` consumed.
+    AlreadySolved,
+    /// `solve_assuming`, `add_at_most` and `solve_qbf` need the problem as
+    /// added, which `preprocess` changed.
+    Simplified,
+    /// `solve_qbf` does not support at-most constraints.
+    QuantifiedAtMost,
+    /// `preprocess` ran out of budget.
+    Stopped(Reason),
+    /// Writing DIMACS failed.
+    Io(io::Error),
 }
 
+// The arena is owned by the solver and only shared with its own problem.
+unsafe impl Send for Solver {}
+
 impl Drop for Solver {
     fn drop(&mut self) {
+        // A problem left in `Todo` must release its memory before the arena.
+        self.work_onto = Task::Done(Solution::Unsatisfiable);
         unsafe {
             let _ = Box::from_raw(self.allocator);
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// No simplification beyond `prepare`.
+    pub(crate) fn nothing() -> Preprocessing {
+        Preprocessing {
+            xor: false,
+            equivalences: false,
+            blocked: false,
+            autarky: false,
+            vivification: false,
+            inprocessing: false,
+            probing: false,
+            elimination: false,
+            addition: false,
+            symmetry: false,
+            cardinality: false,
+        }
+    }
+
+    /// `pigeons` pigeons in `holes` holes, one variable per pair.
+    pub(crate) fn pigeonhole(pigeons: usize, holes: usize) -> Vec<Vec<isize>> {
+        let var = |p: usize, h: usize| (p * holes + h + 1) as isize;
+        let mut clauses: Vec<_> = (0..pigeons)
+            .map(|p| (0..holes).map(|h| var(p, h)).collect())
+            .collect();
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in p + 1..pigeons {
+                    clauses.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        clauses
+    }
+
+    pub(crate) fn solver(variables: usize, clauses: &[Vec<isize>]) -> Solver {
+        let mut solver = Solver::new(variables, clauses.len());
+        clauses
+            .iter()
+            .for_each(|x| solver.add_clause(x.clone()).unwrap());
+        solver
+    }
+
+    /// Random clauses of `width` literals over `variables` from a xorshift
+    /// generator seeded by `seed`.
+    pub(crate) fn random(
+        seed: u64,
+        variables: usize,
+        clauses: usize,
+        width: usize,
+    ) -> Vec<Vec<isize>> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+        (0..clauses)
+            .map(|_| {
+                (0..width)
+                    .map(|_| (next(variables) + 1) as isize * [1, -1][next(2)])
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub(crate) fn satisfies(model: &[isize], clauses: &[Vec<isize>]) -> bool {
+        clauses
+            .iter()
+            .all(|x| x.iter().any(|&l| model[l.unsigned_abs() - 1] == l))
+    }
+
+    /// Whether the clauses are satisfiable, which `solve` and `preprocess`
+    /// with only the passes of `preprocessing` must agree on with a plain
+    /// search. Models, the latter extended by its reconstruction, must
+    /// satisfy the clauses. Returns the statistics of `solve`.
+    pub(crate) fn check(
+        preprocessing: Preprocessing,
+        variables: usize,
+        clauses: &[Vec<isize>],
+    ) -> (bool, Statistics) {
+        let answer = |x: Solution| match x {
+            Solution::Satisfiable(model) => {
+                assert!(satisfies(&model, clauses), "{clauses:?}");
+                true
+            }
+            Solution::Unsatisfiable => false,
+            Solution::Unknown(x) => panic!("stopped by {x:?}"),
+        };
+        let mut plain = solver(variables, clauses);
+        plain.set_preprocessing(nothing());
+        let expected = answer(plain.solve().unwrap());
+
+        let mut simplified = solver(variables, clauses);
+        simplified.set_preprocessing(preprocessing);
+        assert_eq!(answer(simplified.solve().unwrap()), expected);
+
+        let mut preprocessed = solver(variables, clauses);
+        preprocessed.set_preprocessing(preprocessing);
+        let rest = preprocessed.preprocess().unwrap();
+        let rec = preprocessed.reconstruction();
+        let vars = rest.iter().flatten().map(|l| l.unsigned_abs());
+        let vars = vars.chain([variables, rec.variables()]).max().unwrap();
+        let mut remaining = solver(vars, &rest);
+        remaining.set_preprocessing(nothing());
+        let solution = match remaining.solve().unwrap() {
+            Solution::Satisfiable(mut model) => {
+                rec.extend(&mut model);
+                model.truncate(variables);
+                Solution::Satisfiable(model)
+            }
+            x => x,
+        };
+        assert_eq!(answer(solution), expected);
+        (expected, *simplified.statistics())
+    }
+
+    /// Random formulas of a few shapes without tautologies, with their
+    /// number of variables. Some of each shape are unsatisfiable.
+    pub(crate) fn samples() -> Vec<(usize, Vec<Vec<isize>>)> {
+        let shapes = [(10, 30, 3), (8, 40, 3), (12, 20, 2)];
+        let mut res = Vec::new();
+        for seed in 0..6 {
+            for (variables, n, width) in shapes {
+                let mut clauses = random(seed, variables, n, width);
+                clauses.retain(|x| x.iter().all(|l| !x.contains(&-l)));
+                res.push((variables, clauses));
+            }
+        }
+        res
+    }
+
+    /// Whether `counter` of the statistics fired on a satisfiable and on an
+    /// unsatisfiable one of `formulas`, each of which must pass `check`.
+    pub(crate) fn fired(
+        preprocessing: Preprocessing,
+        formulas: &[(usize, Vec<Vec<isize>>)],
+        counter: fn(&Statistics) -> u64,
+    ) -> (bool, bool) {
+        let mut res = (false, false);
+        for (variables, clauses) in formulas {
+            let (sat, stats) = check(preprocessing, *variables, clauses);
+            if counter(&stats) > 0 {
+                match sat {
+                    true => res.0 = true,
+                    false => res.1 = true,
+                }
+            }
+        }
+        res
+    }
+
+    fn written(solver: &Solver) -> String {
+        let mut out = Vec::new();
+        solver.write_simplified(&mut out, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn early_termination() {
+        let mut solver = Solver::new(2, 1);
+        solver.add_clause(vec![1, -2]).unwrap();
+        solver.terminator().terminate();
+        let x = solver.solve();
+        assert!(matches!(x, Ok(Solution::Unknown(Reason::Terminated))));
+        // The request was answered.
+        assert!(matches!(solver.solve(), Ok(Solution::Satisfiable(_))));
+
+        let mut solver = Solver::new(2, 1);
+        solver.add_clause(vec![1, 2]).unwrap();
+        let terminator = solver.terminator();
+        terminator.terminate();
+        let x = solver.preprocess();
+        assert!(matches!(x, Err(SolverError::Stopped(Reason::Terminated))));
+        assert!(!terminator.is_terminated());
+    }
+
+    #[test]
+    fn symmetries_stay_in_search() {
+        let mut solver = solver(12, &pigeonhole(4, 3));
+        solver.set_preprocessing(Preprocessing {
+            symmetry: true,
+            ..nothing()
+        });
+        solver.preprocess().unwrap();
+        let before = written(&solver);
+
+        solver.set_limits(Limits {
+            decisions: Some(0),
+            ..Limits::default()
+        });
+        assert!(matches!(solver.solve(), Ok(Solution::Unknown(_))));
+        assert!(solver.statistics().symmetries > 0);
+        assert_eq!(written(&solver), before);
+    }
+
+    #[test]
+    fn unknown_keeps_problem() {
+        let mut solver = solver(12, &pigeonhole(4, 3));
+        let before = written(&solver);
+        solver.set_limits(Limits {
+            decisions: Some(0),
+            ..Limits::default()
+        });
+        assert!(matches!(solver.solve(), Ok(Solution::Unknown(_))));
+        assert_eq!(written(&solver), before);
+
+        solver.set_limits(Limits::default());
+        assert!(matches!(solver.solve(), Ok(Solution::Unsatisfiable)));
+    }
+
+    #[test]
+    fn assumptions() {
+        let mut solver = Solver::new(3, 3);
+        solver.add_clause(vec![1, 2]).unwrap();
+        solver.add_clause(vec![-1, 3]).unwrap();
+        let Ok(Solution::Satisfiable(model)) = solver.solve_assuming(&[1]) else {
+            panic!()
+        };
+        assert!(model.contains(&1) && model.contains(&3));
+        let x = solver.solve_assuming(&[1, -3]);
+        assert!(matches!(x, Ok(Solution::Unsatisfiable)));
+
+        // The assumptions are gone, later clauses are kept.
+        solver.add_clause(vec![-2]).unwrap();
+        let Ok(Solution::Satisfiable(model)) = solver.solve_assuming(&[]) else {
+            panic!()
+        };
+        assert!(model.starts_with(&[1, -2, 3]));
+        let x = solver.solve_assuming(&[-1]);
+        assert!(matches!(x, Ok(Solution::Unsatisfiable)));
+        assert!(matches!(solver.solve(), Ok(Solution::Satisfiable(_))));
+        let x = solver.solve_assuming(&[1]);
+        assert!(matches!(x, Err(SolverError::AlreadySolved)));
+    }
+
+    #[test]
+    fn growing() {
+        let mut solver = Solver::new(2, 1);
+        solver.add_xor(vec![1, 2]).unwrap();
+        let x = solver.add_clause(vec![1]);
+        assert!(matches!(x, Err(SolverError::TooManyClauses)));
+        let x = solver.solve_assuming(&[1]).unwrap();
+        assert!(matches!(x, Solution::Satisfiable(x) if x == [1, -2]));
+
+        // New variables move the fresh ones, a larger arena the problem.
+        solver.reserve(7, 3).unwrap();
+        solver.add_xor(vec![3, 4, 5, 6, 7]).unwrap();
+        solver.reserve(60, 4000).unwrap();
+        solver.add_clause(vec![-2, 60]).unwrap();
+        let x = solver.solve_assuming(&[-1, -60]);
+        assert!(matches!(x, Ok(Solution::Unsatisfiable)));
+        let Ok(Solution::Satisfiable(model)) = solver.solve_assuming(&[-1, -3, -4, -5]) else {
+            panic!()
+        };
+        assert_eq!(model.len(), 60);
+        assert!(model.starts_with(&[-1, 2, -3, -4, -5]) && model.contains(&60));
+        assert_eq!(model[5].is_positive(), model[6].is_negative());
+
+        let mut solver = self::solver(2, &[vec![1, 2]]);
+        solver.preprocess().unwrap();
+        let x = solver.reserve(3, 2);
+        assert!(matches!(x, Err(SolverError::Simplified)));
+    }
+
+    #[test]
+    fn assumptions_after_simplification() {
+        for seed in 0..20 {
+            let clauses = random(seed, 12, 50, 3);
+            let assumptions = [1, -2, 3];
+            let mut with_units = clauses.clone();
+            with_units.extend(assumptions.iter().map(|&l| vec![l]));
+            let expected = check(Preprocessing::default(), 12, &with_units).0;
+
+            // A stopped search leaves the problem as added.
+            let mut solver = solver(12, &clauses);
+            solver.set_limits(Limits {
+                nodes: Some(0),
+                ..Limits::default()
+            });
+            assert!(matches!(solver.solve(), Ok(Solution::Unknown(_))));
+            solver.set_limits(Limits::default());
+            match solver.solve_assuming(&assumptions).unwrap() {
+                Solution::Satisfiable(model) => {
+                    assert!(expected && satisfies(&model, &with_units))
+                }
+                x => assert!(!expected && matches!(x, Solution::Unsatisfiable)),
+            }
+
+            solver.preprocess().unwrap();
+            let x = solver.solve_assuming(&assumptions);
+            assert!(matches!(x, Err(SolverError::Simplified)));
+        }
+    }
+
+    #[test]
+    fn arena_bounds() {
+        // The estimate overflows, the arena is cut down to `ARENA_BOUND`.
+        let mut solver = solver(1 << 20, &[vec![1, 2], vec![-1], vec![-2, 1 << 20]]);
+        assert!(matches!(solver.solve(), Ok(Solution::Satisfiable(_))));
+
+        // One constraint declared, but it takes many clauses and variables.
+        let mut solver = Solver::new(12, 1);
+        solver.add_xor((1..=12).collect()).unwrap();
+        match solver.solve().unwrap() {
+            Solution::Satisfiable(model) => {
+                assert_eq!(model.iter().filter(|&&l| l > 0).count() % 2, 1)
+            }
+            _ => panic!("parity constraint is satisfiable"),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::time::Duration;
+
+impl fmt::Display for Statistics {
+    /// One DIMACS comment line per counter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "c decisions            {}", self.decisions)?;
+        writeln!(f, "c conflicts            {}", self.conflicts)?;
+        writeln!(f, "c nodes                {}", self.nodes)?;
+        writeln!(f, "c max depth            {}", self.max_depth)?;
+        writeln!(f, "c components           {}", self.components)?;
+        writeln!(f, "c 2-sat subproblems    {}", self.two_sat)?;
+        writeln!(f, "c horn subproblems     {}", self.horn)?;
+        writeln!(f, "c tautologies          {}", self.tautologies)?;
+        writeln!(f, "c pure literals        {}", self.pure_literals)?;
+        writeln!(f, "c universal reductions {}", self.universal_reduced)?;
+        writeln!(f, "c blocked clauses      {}", self.blocked)?;
+        writeln!(f, "c autark clauses       {}", self.autark)?;
+        writeln!(f, "c subsumed clauses     {}", self.subsumed)?;
+        writeln!(f, "c strengthened         {}", self.strengthened)?;
+        writeln!(f, "c vivified literals    {}", self.vivified)?;
+        writeln!(f, "c redundant clauses    {}", self.redundant)?;
+        writeln!(f, "c xor constraints      {}", self.xors)?;
+        writeln!(f, "c xor implied          {}", self.xor_implied)?;
+        writeln!(f, "c xor units            {}", self.xor_units)?;
+        writeln!(f, "c eliminated variables {}", self.eliminated)?;
+        writeln!(f, "c by gate definitions  {}", self.gate_eliminated)?;
+        writeln!(f, "c added variables      {}", self.added)?;
+        writeln!(f, "c at-most constraints  {}", self.at_most)?;
+        writeln!(f, "c exactly-one groups   {}", self.exactly_one)?;
+        writeln!(f, "c symmetries           {}", self.symmetries)?;
+        writeln!(f, "c substituted          {}", self.substituted)?;
+        writeln!(f, "c failed literals      {}", self.failed_literals)?;
+        writeln!(f, "c implied literals     {}", self.implied_literals)?;
+        writeln!(
+            f,
+            "c prepare time         {}ms",
+            self.prepare_time.as_millis()
+        )?;
+        writeln!(
+            f,
+            "c search time          {}ms",
+            self.search_time.as_millis()
+        )?;
+        write!(f, "c peak memory          {} bytes", self.peak_memory)
+    }
+}
+
+/// Counters of the last call to `Solver::solve`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statistics {
+    pub decisions: u64,
+    pub conflicts: u64,
+    /// Branch nodes of the search tree.
+    pub nodes: u64,
+    pub max_depth: usize,
+    /// Independent components the search split the problem into.
+    pub components: u64,
+    /// Subproblems in 2-CNF solved through their implication graph.
+    pub two_sat: u64,
+    /// Subproblems solved as (renamed) Horn formulas by unit resolution.
+    pub horn: u64,
+    pub tautologies: u64,
+    pub pure_literals: u64,
+    /// Universal literals dropped from clauses with only outer existentials.
+    pub universal_reduced: u64,
+    pub blocked: u64,
+    /// Clauses satisfied by autarkies and removed.
+    pub autark: u64,
+    /// Clauses found to be subsumed in `handle_shrinked` or `subsume`.
+    pub subsumed: u64,
+    /// Literals removed from clauses in `handle_shrinked` or `subsume`.
+    pub strengthened: u64,
+    /// Literals removed by vivification.
+    pub vivified: u64,
+    /// Clauses found implied by vivification.
+    pub redundant: u64,
+    /// XOR constraints recognized in the clauses.
+    pub xors: u64,
+    /// Units and equivalences derived by Gauss-Jordan elimination.
+    pub xor_implied: u64,
+    /// Units derived by the elimination on the XOR rows during the search.
+    pub xor_units: u64,
+    /// Variables removed by bounded variable elimination.
+    pub eliminated: u64,
+    /// Those of them defined by a gate, whose clauses were not resolved
+    /// against each other.
+    pub gate_eliminated: u64,
+    /// Fresh variables introduced by bounded variable addition.
+    pub added: u64,
+    /// Native at-most-k constraints replacing clauses.
+    pub at_most: u64,
+    /// Those of them with k = 1 whose literals also form a clause.
+    pub exactly_one: u64,
+    /// Generators of the automorphism group broken by lex-leader clauses.
+    pub symmetries: u64,
+    /// Variables replaced by an equivalent literal.
+    pub substituted: u64,
+    /// Probes that ran into a conflict.
+    pub failed_literals: u64,
+    /// Literals implied by both probes of a variable.
+    pub implied_literals: u64,
+    pub prepare_time: Duration,
+    pub search_time: Duration,
+    /// Highest top of the solver's arena in bytes.
+    pub peak_memory: usize,
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+impl Terminator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the running solve, or the next one, to stop. The request is
+    /// dropped once a solve returned `Solution::Unknown(Reason::Terminated)`.
+    pub fn terminate(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Cloneable handle to stop a solve from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct Terminator(Arc<AtomicBool>);